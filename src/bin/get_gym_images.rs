@@ -29,9 +29,9 @@ async fn main() -> Result<()> {
     let mut client =
         vertical_life::VerticalLifeClient::new(result.access_token, result.refresh_token);
 
-    for gym_id in &config.gyms {
-        info!(?gym_id, "getting gym details");
-        let gym = client.get_gym_details(*gym_id).await?;
+    for gym_config in &config.gyms {
+        info!(?gym_config.id, "getting gym details");
+        let gym = client.get_gym_details(gym_config.id).await?;
         info!(?gym.id, ?gym.name, ?gym.boulder_count, ?gym.route_count, "got gym");
         for gym_sector in gym.gym_sectors.iter() {
             dbg!(&gym_sector);
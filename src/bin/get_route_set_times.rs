@@ -21,9 +21,9 @@ async fn main() -> Result<()> {
 
     let mut all_set_at = vec![];
 
-    for gym_id in &config.gyms {
-        info!(?gym_id, "getting gym details");
-        let gym = client.get_gym_details(*gym_id).await?;
+    for gym_config in &config.gyms {
+        info!(?gym_config.id, "getting gym details");
+        let gym = client.get_gym_details(gym_config.id).await?;
         info!(?gym.id, ?gym.name, ?gym.boulder_count, ?gym.route_count, "got gym");
         for gym_sector in gym.gym_sectors.iter() {
             let sector = client.get_gym_sector(gym_sector.id).await?;
@@ -38,7 +38,7 @@ async fn main() -> Result<()> {
 
     // Distribute datetimes in all_set_at to hourly buckets and print how many are in each bucket
     // Print hour as 00:00, zero-padded
-    let mut buckets = vec![0; 24];
+    let mut buckets = [0; 24];
     for set_at in all_set_at {
         buckets[set_at.hour() as usize] += 1;
     }
@@ -1,17 +1,62 @@
 #![allow(dead_code, unused_imports, unused_variables)]
-use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
-use climbsheet::{climb_sheet::ClimbSheet, config, setup, sheets, vertical_life};
+use clap::{Parser, Subcommand};
+use climbsheet::{
+    climb_sheet::ClimbSheet,
+    config, email, feed, history, html, notify, setters, setup, sheets,
+    sink::{FileFormat, FileSink, Sink},
+    stats, vertical_life,
+};
 use eyre::Result;
 use secrecy::ExposeSecret;
 use tracing::*;
 
+#[derive(Parser, Debug)]
+#[command(about = "Sync climbs from Vertical Life to Google Sheets")]
+struct Args {
+    /// Print the changes that would be made to the spreadsheet without writing anything
+    #[arg(long)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the climbs as a static HTML site instead of syncing them to the spreadsheet. No
+    /// Google credentials are needed.
+    ExportHtml {
+        /// Directory the site is written to
+        #[arg(long, default_value = "site")]
+        out: PathBuf,
+    },
+    /// Print each setter's climb count, grades and climbs set per month for each gym
+    Setters {
+        /// Print CSV instead of a table
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Find Vertical Life gyms
+    Gyms {
+        #[command(subcommand)]
+        command: GymsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum GymsCommand {
+    /// Print the id, name, city and climb counts of the gyms whose name matches, as lines that can
+    /// be pasted to gyms in config.toml
+    Search { name: String },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
     setup::setup()?;
     let config = config::read_config();
-    let climbsheet = ClimbSheet::new(&config).await?;
-    info!(?config.gyms, "starting with config");
+    info!(?config.gyms, ?args.dry_run, "starting with config");
 
     let result = vertical_life::VerticalLifeAuthClient::do_auth_flow(
         &config.vertical_life_email,
@@ -20,32 +65,244 @@ async fn main() -> Result<()> {
     .await?;
     let mut client =
         vertical_life::VerticalLifeClient::new(result.access_token, result.refresh_token);
+
+    match args.command {
+        Some(Command::ExportHtml { out }) => export_html(&config, &mut client, &out).await,
+        Some(Command::Setters { csv }) => print_setters(&config, &mut client, csv).await,
+        Some(Command::Gyms {
+            command: GymsCommand::Search { name },
+        }) => search_gyms(&mut client, &name).await,
+        None => match &config.output {
+            config::Output::Sheets => {
+                let mut climbsheet = ClimbSheet::new(&config).await?;
+                sync(&config, &mut client, &mut climbsheet, args.dry_run).await
+            }
+            config::Output::Csv { directory } => {
+                let mut sink = FileSink::new(&config, directory, FileFormat::Csv);
+                sync(&config, &mut client, &mut sink, args.dry_run).await
+            }
+            config::Output::Json { directory } => {
+                let mut sink = FileSink::new(&config, directory, FileFormat::Json);
+                sync(&config, &mut client, &mut sink, args.dry_run).await
+            }
+        },
+    }
+}
+
+/// Gym's details and its sectors with their walls and climbs
+async fn get_gym(
+    client: &mut vertical_life::VerticalLifeClient,
+    gym_id: u32,
+) -> Result<(vertical_life::Gym, Vec<vertical_life::GymSectorFull>)> {
+    info!(?gym_id, "getting gym details");
+    let gym = client.get_gym_details(gym_id).await?;
+    info!(?gym.id, ?gym.name, ?gym.boulder_count, ?gym.route_count, "got gym");
+
+    let mut sectors = vec![];
+    for gym_sector in gym.gym_sectors.iter() {
+        info!(?gym_sector.id, "getting gym sector");
+        let sector = client.get_gym_sector(gym_sector.id).await?;
+        for wall in sector.walls.iter() {
+            info!(?wall.name, ?wall.category, ?wall.height, "got wall");
+        }
+        sectors.push(sector);
+    }
+    Ok((gym, sectors))
+}
+
+async fn export_html(
+    config: &config::Config,
+    client: &mut vertical_life::VerticalLifeClient,
+    out: &Path,
+) -> Result<()> {
+    let mut pages = vec![];
+    for gym_config in &config.gyms {
+        let (gym, sectors) = get_gym(client, gym_config.id).await?;
+        pages.extend(stats::gym_tabs(config, &gym, &sectors)?);
+    }
+    html::write_site(config, out, &pages)?;
+    info!(?out, pages = pages.len(), "done");
+    Ok(())
+}
+
+async fn print_setters(
+    config: &config::Config,
+    client: &mut vertical_life::VerticalLifeClient,
+    csv: bool,
+) -> Result<()> {
+    let mut gyms = vec![];
+    for gym_config in &config.gyms {
+        let (gym, sectors) = get_gym(client, gym_config.id).await?;
+        let climbs = sectors
+            .iter()
+            .flat_map(|s| s.walls.iter())
+            .flat_map(|w| w.climbs())
+            .cloned()
+            .collect::<Vec<_>>();
+        gyms.push((gym.name, climbs));
+    }
+    let (header, rows) = setters::report_rows(&setters::setter_stats(
+        gyms.iter()
+            .map(|(name, climbs)| (name.as_str(), climbs.as_slice())),
+    ));
+    if csv {
+        print!("{}", setters::format_csv(&header, &rows)?);
+    } else {
+        print!("{}", setters::format_table(&header, &rows));
+    }
+    Ok(())
+}
+
+async fn search_gyms(client: &mut vertical_life::VerticalLifeClient, name: &str) -> Result<()> {
+    let results = client.search_gyms(name).await?;
+    if results.is_empty() {
+        println!("No gyms found for '{name}'");
+    }
+    for result in &results {
+        // Search results don't have the climb counts
+        let gym = client.get_gym_details(result.id).await?;
+        println!("{}", vertical_life::GymSummary::new(result, &gym));
+    }
+    Ok(())
+}
+
+async fn sync(
+    config: &config::Config,
+    client: &mut vertical_life::VerticalLifeClient,
+    sink: &mut impl Sink,
+    dry_run: bool,
+) -> Result<()> {
     let mut new_climbs = vec![];
+    let mut notifications = vec![];
+    let mut gym_climbs = vec![];
+    let mut stats_tabs = vec![];
+    let run_at = chrono::Utc::now();
+    let mut history = match &config.history_db_path {
+        Some(path) if !dry_run => Some(history::History::open(path)?),
+        _ => None,
+    };
+
+    let mut gyms = vec![];
+    for gym_config in &config.gyms {
+        gyms.push(get_gym(client, gym_config.id).await?);
+    }
+    config.check_sheet_names(gyms.iter().map(|(gym, _)| gym))?;
 
-    for gym_id in &config.gyms {
-        info!(?gym_id, "getting gym details");
-        let gym = client.get_gym_details(*gym_id).await?;
-        // Get existing climbs from spreadsheet for the gym, so that we can check in
-        // add_wall_to_sheet if the climb already exists in the sheet, and skip adding it
-        let gym_sheet_routes = climbsheet.get_gym_routes_from_sheet(&gym).await?;
-        let gym_sheet_routes_set: HashSet<_> = gym_sheet_routes.into_iter().collect();
-
-        info!(?gym.id, ?gym.name, ?gym.boulder_count, ?gym.route_count, "got gym");
-        for gym_sector in gym.gym_sectors.iter() {
-            info!(?gym_sector.id, "getting gym sector");
-            let sector = client.get_gym_sector(gym_sector.id).await?;
-            for wall in sector.walls.iter() {
-                info!(?wall.name, ?wall.category, ?wall.height, "got wall");
-
-                new_climbs.extend_from_slice(
-                    &climbsheet
-                        .add_wall_to_sheet(&gym_sheet_routes_set, &gym, wall)
-                        .await?,
+    for (gym, sectors) in gyms {
+        let missing_sheets = sink.missing_gym_sheets(&gym, &sectors)?;
+        if dry_run {
+            for sheet_name in &missing_sheets {
+                println!("Would create sheet '{sheet_name}'");
+            }
+        } else {
+            sink.create_sheets(&gym, &missing_sheets).await?;
+        }
+
+        // Compare existing climbs in the output to the ones in Vertical Life
+        let diff = sink.diff_gym(&gym, &sectors).await?;
+        let changelog_row = diff.changelog_row(run_at);
+        if dry_run {
+            println!("{diff}");
+            println!(
+                "Would append to '{}': {}",
+                config.changelog_sheet_name,
+                changelog_row.join(" | ")
+            );
+        } else {
+            sink.apply_gym_diff(&diff).await?;
+            if let Err(err) = sink.append_changelog_row(changelog_row).await {
+                error!(?err, ?gym.name, "failed to append changelog row");
+            }
+        }
+
+        let climbs = sectors
+            .iter()
+            .flat_map(|s| s.walls.iter())
+            .flat_map(|w| w.climbs())
+            .collect::<Vec<_>>();
+        if let Some(history) = &mut history {
+            let update = history.record_gym(gym.id, &gym.name, &climbs, run_at)?;
+            info!(?gym.name, ?update, "recorded history");
+        } else if dry_run {
+            if let Some(path) = &config.history_db_path {
+                println!(
+                    "Would record {} climb(s) to {}",
+                    climbs.len(),
+                    path.display()
                 );
             }
         }
 
-        climbsheet.highlight_new_routes(&gym).await?;
+        stats_tabs.extend(stats::gym_tabs(config, &gym, &sectors)?);
+        new_climbs.extend(diff.new_climbs().cloned());
+        notifications.push(notify::GymNotification::from(&diff));
+        gym_climbs.push(feed::GymClimbs {
+            gym_name: gym.name.clone(),
+            climbs: climbs.into_iter().cloned().collect(),
+        });
+    }
+
+    let reports = [
+        (
+            &config.stats_sheet_name,
+            stats::stats_rows(
+                stats_tabs
+                    .iter()
+                    .map(|p| (p.title.as_str(), p.climbs.as_slice())),
+            ),
+        ),
+        (
+            &config.setters_sheet_name,
+            setters::report_rows(&setters::setter_stats(
+                gym_climbs
+                    .iter()
+                    .map(|g| (g.gym_name.as_str(), g.climbs.as_slice())),
+            )),
+        ),
+    ];
+    if dry_run {
+        for (sheet_name, (_, rows)) in &reports {
+            println!("Would write {} row(s) to '{sheet_name}'", rows.len());
+        }
+        if !config.webhooks.is_empty() {
+            println!("Would notify {} webhook(s)", config.webhooks.len());
+        }
+        if let Some(feed_config) = &config.feeds {
+            for (path, feed) in feed::build_feeds(feed_config, &gym_climbs, chrono::Utc::now()) {
+                println!(
+                    "Would write feed {} with {} entries",
+                    path.display(),
+                    feed.entries.len()
+                );
+            }
+        }
+        if let (Some(email_config), Some(digest)) = (&config.email, email::digest(&notifications)) {
+            println!(
+                "Would email '{}' to {}:\n{}",
+                digest.subject,
+                email_config.to.join(", "),
+                digest.text
+            );
+        }
+    } else {
+        notify::send_webhooks(&config.webhooks, &notifications).await;
+        if let Some(feed_config) = &config.feeds {
+            if let Err(err) = feed::write_feeds(feed_config, &gym_climbs) {
+                error!(?err, "failed to write feeds");
+            }
+        }
+        if let Some(email_config) = &config.email {
+            if let Err(err) = email::send_digest(email_config, &notifications).await {
+                error!(?err, "failed to send email digest");
+            }
+        }
+        // Reports go last, and like notifications, a failure only gets logged, since the climbs
+        // are already synced
+        for (sheet_name, (header, rows)) in reports {
+            if let Err(err) = sink.write_report(sheet_name, header, rows).await {
+                error!(?err, sheet_name, "failed to write report");
+            }
+        }
     }
 
     info!(?new_climbs, "done");
@@ -1,324 +1,790 @@
-use std::collections::HashSet;
+use std::{collections::HashMap, fmt};
 
 use crate::{
-    config,
+    config::{self, column_position, untitled_column_count, Column, ColumnField},
+    diff::{self, GymDiff},
+    grades::Grade,
     sheets::{self, Row, SheetsClient, Spreadsheet},
+    sink::Sink,
     vertical_life,
 };
+use async_trait::async_trait;
+use chrono::NaiveDate;
 use eyre::Result;
-use google_sheets4::api::{GridRange, Sheet};
+use google_sheets4::api::{CellData, GridRange, Request, Sheet};
 use tracing::*;
 
+/// Format of the dates in archive tab
 const HUMAN_DATE_FORMAT: &str = "%-d.%-m.%Y";
-const NEW_ROUTE_WITHIN_DAYS: i64 = 7;
 
 /// Spreadsheet rows of type Vec<String> are parsed to these to make them a bit
 /// more comprehensible
 #[derive(Debug, Eq, PartialEq, Hash)]
 pub struct ClimbSheetRow {
-    route_card_label: String,
-    difficulty: String,
-    set_at_human_date: String,
-    route_setter: String,
-    parent_name: String,
+    /// Values of the columns that describe the climb, i.e. all but the link, hidden and converted
+    /// grade columns
+    values: Vec<String>,
+    set_at: Option<NaiveDate>,
 }
 
 impl vertical_life::Climb {
-    pub fn to_sheet_row(&self) -> Row {
-        vec![
-            self.route_card_label.to_string(),
-            self.difficulty.to_string(),
-            self.set_at.format(HUMAN_DATE_FORMAT).to_string(),
-            self.route_setter.to_string(),
-            self.parent_name.to_string(),
-            format!(r#"=HYPERLINK("{}"; "🔗")"#, self.share_url),
-        ]
+    pub fn to_sheet_row(&self, columns: &[Column]) -> Row {
+        columns.iter().map(|c| c.sheet_value(self)).collect()
+    }
+
+    /// Same as to_sheet_row, but as cells to be used with batch update requests
+    pub fn to_sheet_cells(&self, columns: &[Column]) -> Vec<CellData> {
+        columns.iter().map(|c| c.sheet_cell(self)).collect()
+    }
+}
+
+impl Column {
+    /// Value of the column for climb as it would be entered by the user
+    pub fn sheet_value(&self, climb: &vertical_life::Climb) -> String {
+        match self.field {
+            ColumnField::Color => String::new(),
+            ColumnField::RouteCardLabel => climb.route_card_label.to_string(),
+            ColumnField::Difficulty => climb.difficulty.to_string(),
+            ColumnField::SetAt => climb.set_at.format(self.format()).to_string(),
+            ColumnField::RouteSetter => climb.route_setter.to_string(),
+            ColumnField::ParentName => climb.parent_name.to_string(),
+            ColumnField::SectorName => climb.sector_name.to_string(),
+            ColumnField::ShareUrl => self.hyperlink_formula(&climb.share_url),
+            ColumnField::Id => climb.id.to_string(),
+            ColumnField::GradeOrder => Grade::of_climb(climb)
+                .map(|g| g.key.to_string())
+                .unwrap_or_default(),
+            ColumnField::ConvertedDifficulty => self.converted_difficulty(climb),
+        }
+    }
+
+    /// Climb's grade in the first of column's grade systems that applies to it, or empty if none
+    /// does or the grade is unknown
+    fn converted_difficulty(&self, climb: &vertical_life::Climb) -> String {
+        let Some(grade) = Grade::of_climb(climb) else {
+            return String::new();
+        };
+        self.grade_systems()
+            .into_iter()
+            .filter(|system| *system != grade.system)
+            .find_map(|system| grade.convert(system))
+            .unwrap_or_default()
+    }
+
+    /// Link to url with the column's format as the text, for example
+    /// `=HYPERLINK("https://..."; "🔗")`
+    pub fn hyperlink_formula(&self, url: &str) -> String {
+        format!(r#"=HYPERLINK("{}"; "{}")"#, url, self.format())
+    }
+
+    fn sheet_cell(&self, climb: &vertical_life::Climb) -> CellData {
+        let mut cell = match self.field {
+            ColumnField::SetAt => sheets::date_cell(
+                climb.set_at.date_naive(),
+                &sheets::date_pattern_from_chrono_format(self.format()),
+            ),
+            ColumnField::ShareUrl => sheets::formula_cell(&self.sheet_value(climb)),
+            ColumnField::Id => sheets::number_cell(climb.id as f64),
+            ColumnField::GradeOrder => match Grade::of_climb(climb) {
+                Some(grade) => sheets::number_cell(grade.key as f64),
+                None => sheets::string_cell(""),
+            },
+            _ => sheets::string_cell(&self.sheet_value(climb)),
+        };
+        if self.has_color() {
+            cell.user_entered_format
+                .get_or_insert_with(Default::default)
+                .background_color = Some(sheets::color_from_hex(&climb.color));
+        }
+        cell
     }
 }
 
 impl ClimbSheetRow {
-    // Figure out if row is "new". It's new if the human date in format of d.m.yyyy is within last
-    // NEW_ROUTE_WITHIN_DAYS days
-    pub fn is_new(&self) -> bool {
+    /// Parse row of values that start from the table's first column
+    pub fn parse(row: &[String], columns: &[Column]) -> Self {
+        let value = |idx: usize| row.get(idx).cloned().unwrap_or_default();
+        let values = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| {
+                !c.field.is_hidden()
+                    && !matches!(
+                        c.field,
+                        ColumnField::Color
+                            | ColumnField::ShareUrl
+                            | ColumnField::ConvertedDifficulty
+                    )
+            })
+            .map(|(idx, _)| value(idx))
+            .collect();
+        let set_at = columns
+            .iter()
+            .position(|c| c.field == ColumnField::SetAt)
+            .and_then(|idx| NaiveDate::parse_from_str(&value(idx), columns[idx].format()).ok());
+        Self { values, set_at }
+    }
+
+    pub fn from_climb(climb: &vertical_life::Climb, columns: &[Column]) -> Self {
+        Self::parse(&climb.to_sheet_row(columns), columns)
+    }
+
+    // Figure out if row is "new". It's new if the date is within last new_climb_days days
+    pub fn is_new(&self, new_climb_days: i64) -> bool {
         let next_midnight = chrono::Utc::now().date_naive() + chrono::Duration::days(1);
-        let set_at = chrono::NaiveDate::parse_from_str(&self.set_at_human_date, HUMAN_DATE_FORMAT)
-            .expect("Failed to parse date");
-        let days_since_set = next_midnight.signed_duration_since(set_at).num_days();
-        days_since_set <= NEW_ROUTE_WITHIN_DAYS + 1 // +1 because we compare against the next midnight
+        match self.set_at {
+            Some(set_at) => {
+                let days_since_set = next_midnight.signed_duration_since(set_at).num_days();
+                days_since_set <= new_climb_days + 1 // +1 because we compare against the next midnight
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_at_date(&self) -> Option<NaiveDate> {
+        self.set_at
     }
 }
 
-impl From<&vertical_life::Climb> for ClimbSheetRow {
-    fn from(climb: &vertical_life::Climb) -> Self {
-        climb.to_sheet_row().into()
+impl fmt::Display for ClimbSheetRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.values.join(" | "))
     }
 }
 
-impl From<Vec<String>> for ClimbSheetRow {
-    fn from(row: Vec<String>) -> Self {
-        // Start from first non empty element
-        // For some reason, row might not have the first column with background color as ""
-        let row = row
-            .into_iter()
-            .skip_while(|s| s.is_empty())
-            .collect::<Vec<_>>();
+/// One of gym's sheets and its rows, not including the header
+#[derive(Debug)]
+pub struct GymSheet {
+    pub sheet_name: String,
+    pub sheet_id_num: i32,
+    /// Zero indexed column where the table starts, i.e. the first non-empty header column
+    pub first_column_idx: i32,
+    pub rows: Vec<GymSheetRow>,
+}
+
+/// A row in one of gym's sheets, with the location of the row in the spreadsheet
+#[derive(Debug)]
+pub struct GymSheetRow {
+    pub sheet_name: String,
+    pub sheet_id_num: i32,
+    /// Zero indexed row number, header being row 0
+    pub row_idx: i32,
+    /// Vertical Life id of the climb, missing from rows added before ids were written to sheet
+    pub climb_id: Option<u32>,
+    pub row: ClimbSheetRow,
+    /// Values of the row's cells as they would be entered by the user, starting from the table's
+    /// first column
+    pub values: Row,
+    /// Background colors of the row's cells as hex, empty for cells without one
+    pub colors: Vec<String>,
+}
+
+impl GymSheetRow {
+    pub fn new(
+        sheet_name: &str,
+        sheet_id_num: i32,
+        row_idx: i32,
+        values: Row,
+        colors: Vec<String>,
+        columns: &[Column],
+    ) -> Self {
+        let climb_id = column_position(columns, ColumnField::Id)
+            .and_then(|idx| values.get(idx))
+            .and_then(|v| v.parse().ok());
         Self {
-            route_card_label: row[0].clone(),
-            difficulty: row[1].clone(),
-            set_at_human_date: row[2].clone(),
-            route_setter: row[3].clone(),
-            parent_name: row[4].clone(),
+            sheet_name: sheet_name.to_string(),
+            sheet_id_num,
+            row_idx,
+            climb_id,
+            row: ClimbSheetRow::parse(&values, columns),
+            values,
+            colors,
         }
     }
+
+    /// Row as it should appear in gym's archive tab: the original columns followed by the date
+    /// the climb was removed at and how many days it was up
+    pub fn to_archive_row(&self, removed_at: NaiveDate, column_count: usize) -> Row {
+        let days_up = self
+            .row
+            .set_at_date()
+            .map(|set_at| {
+                removed_at
+                    .signed_duration_since(set_at)
+                    .num_days()
+                    .to_string()
+            })
+            .unwrap_or_default();
+        self.values
+            .iter()
+            .cloned()
+            // Pad rows that are missing trailing columns, so that the extra columns line up
+            .chain(std::iter::repeat(String::new()))
+            .take(column_count)
+            .chain([removed_at.format(HUMAN_DATE_FORMAT).to_string(), days_up])
+            .collect()
+    }
 }
 
 pub struct ClimbSheet<'a> {
-    sheet_id: String,
     config: &'a config::Config,
     sheet_client: SheetsClient,
-    spreadsheet: Spreadsheet,
+    /// Every spreadsheet that is synced to, by spreadsheet id. Gyms can have their own
+    /// spreadsheet, see config::GymConfig::sheet_id.
+    spreadsheets: HashMap<String, Spreadsheet>,
 }
 
 impl<'a> ClimbSheet<'a> {
     pub async fn new(config: &'a config::Config) -> Result<ClimbSheet<'a>> {
+        if config.sheet_id.is_empty() {
+            eyre::bail!("sheet_id is missing from config");
+        }
         let sheet_client = sheets::get_client(&config.service_account_credentials_path).await?;
-        let spreadsheet = sheets::get_spreadsheet(&sheet_client, &config.sheet_id).await?;
+        let mut spreadsheets = HashMap::new();
+        for sheet_id in config.sheet_ids() {
+            let spreadsheet = sheets::get_spreadsheet(&sheet_client, sheet_id).await?;
+            spreadsheets.insert(sheet_id.to_string(), spreadsheet);
+        }
 
         Ok(Self {
-            sheet_id: config.sheet_id.clone(),
             config,
             sheet_client,
-            spreadsheet,
+            spreadsheets,
         })
     }
 
-    /// For a gym, return rows from the spreadsheets all sheets (pages) that belong to the gym For
-    /// example, for Ristikko, you would return rows from Ristikko - Reitit and Ristikko - Boulderit
-    /// pages
-    pub async fn get_gym_routes_from_sheet(
-        &self,
-        gym: &vertical_life::Gym,
-    ) -> Result<Vec<ClimbSheetRow>> {
-        info!(?gym, "getting gym routes from sheet");
-
-        let gym_sheets = self.get_gym_sheets(gym).await?;
-        let gym_sheet_names = gym_sheets
-            .into_iter()
-            .map(|s| s.properties.as_ref().unwrap().title.as_ref().unwrap());
-        let sheets_rows = futures::future::join_all(gym_sheet_names.map(|sheet_name| async move {
-            sheets::get_sheet_rows(&self.sheet_client, &self.config.sheet_id, sheet_name)
-                .await
-                .map(|rows| {
-                    rows.into_iter()
-                        // Skip the header row
-                        .skip(1)
-                        .map(ClimbSheetRow::from)
-                        .collect::<Vec<_>>()
-                })
-        }))
-        .await
-        .into_iter()
-        .collect::<Result<Vec<_>>>()?;
-
-        Ok(sheets_rows.into_iter().flatten().collect())
+    /// Fetch the spreadsheet again to include sheets that were added to it
+    async fn refresh_spreadsheet(&mut self, sheet_id: &str) -> Result<()> {
+        let spreadsheet = sheets::get_spreadsheet(&self.sheet_client, sheet_id).await?;
+        self.spreadsheets.insert(sheet_id.to_string(), spreadsheet);
+        Ok(())
     }
 
-    pub async fn add_wall_to_sheet(
-        &self,
-        gym_sheet_routes: &HashSet<ClimbSheetRow>,
-        gym: &vertical_life::Gym,
-        wall: &vertical_life::Wall,
-    ) -> Result<Vec<vertical_life::Climb>> {
-        let mut new_climbs = vec![];
-        let (sheet_name, sheet_id_num) =
-            self.get_sheet_for_gym_name_and_wall_category(&gym.name, &wall.category);
-
-        for climb in wall.climbs() {
-            info!(?climb, "got climb");
-            if gym_sheet_routes.contains(&climb.into()) {
-                info!(?climb, "climb already exists in sheet, skipping");
-                continue;
-            }
+    /// Climb as a row of cells that starts from the first column of sheet, so that the table's
+    /// columns, beginning from first_column_idx, line up
+    fn climb_to_cells(
+        climb: &vertical_life::Climb,
+        columns: &[Column],
+        first_column_idx: i32,
+    ) -> Vec<CellData> {
+        let mut cells = vec![CellData::default(); first_column_idx as usize];
+        cells.extend(climb.to_sheet_cells(columns));
+        cells
+    }
 
-            self.append_climb_to_sheet(&sheet_name, sheet_id_num, climb)
-                .await?;
+    fn find_sheet(&self, sheet_id: &str, sheet_name: &str) -> Option<&Sheet> {
+        self.spreadsheets
+            .get(sheet_id)
+            .and_then(|s| s.sheets.as_ref())
+            .into_iter()
+            .flatten()
+            .find(|s| s.properties.as_ref().and_then(|p| p.title.as_deref()) == Some(sheet_name))
+    }
 
-            new_climbs.push(climb.to_owned());
+    pub async fn highlight_new_routes(&self, gym: &vertical_life::Gym) -> Result<()> {
+        info!(?gym.id, "highlighting new routes");
+        for sheet in self.get_gym_routes_from_sheet(gym).await? {
+            let new_row_idxs = sheet
+                .rows
+                .iter()
+                .enumerate()
+                .filter(|(_, row)| row.row.is_new(self.config.gym_new_climb_days(gym.id)))
+                .map(|(idx, _)| idx)
+                .collect::<Vec<_>>();
+            sheets::batch_update(
+                &self.sheet_client,
+                self.config.gym_sheet_id(gym.id),
+                self.highlight_requests(
+                    gym.id,
+                    sheet.sheet_id_num,
+                    sheet.first_column_idx,
+                    &new_row_idxs,
+                ),
+            )
+            .await?;
         }
 
-        sheets::sort_sheet_by_column(
-            &self.sheet_client,
-            &self.sheet_id,
-            sheet_id_num,
-            self.config.date_column_idx,
-        )
-        .await?;
-        Ok(new_climbs)
+        Ok(())
     }
 
-    async fn append_climb_to_sheet(
+    /// Reset the date column's background and highlight it for the rows of the new climbs, given
+    /// as zero indexed positions below the header. Nothing is highlighted if there is no date
+    /// column.
+    fn highlight_requests(
         &self,
-        sheet_name: &str,
+        gym_id: u32,
         sheet_id_num: i32,
-        climb: &vertical_life::Climb,
-    ) -> Result<()> {
-        let res = sheets::append_row(
-            &self.sheet_client,
-            &self.sheet_id,
-            sheet_name,
-            climb.to_sheet_row(),
-        )
-        .await?;
-        let row_n = sheets::get_updated_row_from_update_values_response(&res);
-        sheets::set_range_background_color(
-            &self.sheet_client,
-            &self.sheet_id,
-            Some(sheets::color_from_hex(&climb.color)),
-            GridRange {
-                sheet_id: Some(sheet_id_num),
-                start_row_index: Some(row_n),
-                end_row_index: Some(row_n + 1),
-                start_column_index: Some(self.config.climb_color_column_idx),
-                end_column_index: Some(self.config.climb_color_column_idx + 1),
-            },
-        )
-        .await?;
-        tokio::time::sleep(std::time::Duration::from_millis(6000)).await;
-        Ok(())
+        first_column_idx: i32,
+        new_row_idxs: &[usize],
+    ) -> Vec<Request> {
+        let Some(date_column_idx) =
+            column_position(self.config.gym_columns(gym_id), ColumnField::SetAt)
+                .map(|idx| first_column_idx + idx as i32)
+        else {
+            return vec![];
+        };
+        let date_column = |start_row_index, end_row_index| GridRange {
+            sheet_id: Some(sheet_id_num),
+            start_row_index: Some(start_row_index),
+            end_row_index,
+            start_column_index: Some(date_column_idx),
+            end_column_index: Some(date_column_idx + 1),
+        };
+
+        let mut requests = vec![sheets::background_color_request(None, date_column(1, None))];
+        let new_color = sheets::color_from_hex(self.config.gym_new_climb_background_color(gym_id));
+        // Consecutive rows are highlighted with one request, which with the default sorting by
+        // date means just one request for all the new rows
+        let mut idxs = new_row_idxs.iter().copied().peekable();
+        while let Some(start_idx) = idxs.next() {
+            let mut end_idx = start_idx + 1;
+            while idxs.next_if_eq(&end_idx).is_some() {
+                end_idx += 1;
+            }
+            requests.push(sheets::background_color_request(
+                Some(new_color.clone()),
+                // +1 to account for the header row
+                date_column(start_idx as i32 + 1, Some(end_idx as i32 + 1)),
+            ));
+        }
+        requests
     }
 
-    /// Find sheet in spreadsheet that matches gym's name and the wall category
-    /// For example, for gym_name "Kiipeilyareena Ristikko" and wall_category "gym_bouldering"
-    /// this should return "Ristikko - Boulderit" and it's numeric zero-indexed sheet id
-    fn get_sheet_for_gym_name_and_wall_category(
-        &self,
-        gym_name: &str,
-        wall_category: &str,
-    ) -> (String, i32) {
-        let sheets = self.spreadsheet.sheets.as_ref().unwrap();
-        let location_name = parse_location_from_gym_name(gym_name);
-        let sheet_name = format_sheet_name(
-            location_name,
-            &wall_category_to_plural_human_type(wall_category),
-        );
-        let sheet = sheets
+    /// Returns gym's sheets that hold current climbs, matched exactly by name, so gym's archive
+    /// tab is not included
+    pub async fn get_gym_sheets(&self, gym: &vertical_life::Gym) -> Result<Vec<&Sheet>> {
+        let sheet_id = self.config.gym_sheet_id(gym.id);
+        let sheet_names = self.config.gym_sheet_names(gym)?;
+        Ok(sheet_names
             .iter()
-            .find(|s| s.properties.as_ref().unwrap().title == Some(sheet_name.to_string()))
-            .unwrap_or_else(|| panic!("sheet '{sheet_name}' not found in spreadsheet"));
-
-        let sheet_id_num = sheet.properties.as_ref().unwrap().sheet_id.unwrap();
-        (sheet_name, sheet_id_num)
+            .filter_map(|sheet_name| self.find_sheet(sheet_id, sheet_name))
+            .collect())
     }
+}
 
-    pub async fn reset_grade_column_background(&self, sheet_id_num: i32) -> Result<()> {
-        info!(?sheet_id_num, "resetting grade column background");
-        sheets::set_range_background_color(
-            &self.sheet_client,
-            &self.sheet_id,
-            None,
-            GridRange {
-                sheet_id: Some(sheet_id_num),
-                start_row_index: Some(1),
-                end_row_index: None,
-                start_column_index: Some(self.config.date_column_idx),
-                end_column_index: Some(self.config.date_column_idx + 1),
-            },
-        )
-        .await?;
+#[async_trait]
+impl Sink for ClimbSheet<'_> {
+    fn config(&self) -> &config::Config {
+        self.config
+    }
 
-        Ok(())
+    fn has_sheet(&self, gym_id: u32, sheet_name: &str) -> bool {
+        self.find_sheet(self.config.gym_sheet_id(gym_id), sheet_name)
+            .is_some()
     }
 
-    pub async fn highlight_new_routes(&self, gym: &vertical_life::Gym) -> Result<()> {
-        info!(?gym.id, "highlighting new routes");
+    /// For a gym, return rows from the spreadsheets all sheets (pages) that belong to the gym For
+    /// example, for Ristikko, you would return rows from Ristikko - Reitit and Ristikko - Boulderit
+    /// pages
+    async fn get_gym_routes_from_sheet(&self, gym: &vertical_life::Gym) -> Result<Vec<GymSheet>> {
+        info!(?gym, "getting gym routes from sheet");
+
+        let sheet_id = self.config.gym_sheet_id(gym.id);
+        let columns = self.config.gym_columns(gym.id);
         let gym_sheets = self.get_gym_sheets(gym).await?;
-        for sheet in gym_sheets {
-            let sheet_name = sheet.properties.as_ref().unwrap().title.as_ref().unwrap();
-            let rows =
-                sheets::get_sheet_rows(&self.sheet_client, &self.config.sheet_id, sheet_name)
-                    .await
-                    .map(|rows| {
-                        rows.into_iter()
-                            // Skip the header row
-                            .skip(1)
-                            .map(ClimbSheetRow::from)
-                            .collect::<Vec<_>>()
-                    })?;
-
-            // Find last index in rows that would be still considered a new route
-            // (i.e. it was added within the last week)
-            let last_new_route_idx = rows
-                .iter()
+        futures::future::join_all(gym_sheets.into_iter().map(|sheet| async move {
+            let properties = sheet.properties.as_ref().unwrap();
+            let sheet_name = properties.title.as_ref().unwrap();
+            let sheet_id_num = properties.sheet_id.unwrap();
+            let mut rows = sheets::get_sheet_cells(&self.sheet_client, sheet_id, sheet_name)
+                .await?
+                .into_iter();
+            let first_column_idx = rows
+                .next()
+                .map(|header| table_start(&header, columns))
+                .unwrap_or(0);
+            let rows = rows
                 .enumerate()
-                .rev()
-                .find(|(_, row)| row.is_new())
-                .map(|(idx, _)| idx);
+                // Rows that only have formatting come back too
+                .filter(|(_, cells)| {
+                    cells
+                        .iter()
+                        .any(|c| !sheets::cell_formatted_value(c).is_empty())
+                })
+                .map(|(idx, cells)| {
+                    let cells = &cells[(first_column_idx as usize).min(cells.len())..];
+                    GymSheetRow::new(
+                        sheet_name,
+                        sheet_id_num,
+                        // +1 because the header row was already consumed
+                        idx as i32 + 1,
+                        cells.iter().map(sheets::cell_user_entered_value).collect(),
+                        cells.iter().map(cell_background_color).collect(),
+                        columns,
+                    )
+                })
+                .collect();
 
-            let sheet_id_num = sheet.properties.as_ref().unwrap().sheet_id.unwrap();
-            self.reset_grade_column_background(sheet_id_num).await?;
+            Ok(GymSheet {
+                sheet_name: sheet_name.to_string(),
+                sheet_id_num,
+                first_column_idx,
+                rows,
+            })
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
 
-            if let Some(last_new_route_idx) = last_new_route_idx {
-                sheets::set_range_background_color(
+    /// Write the changes in gym diff to the spreadsheet
+    async fn apply_gym_diff(&self, diff: &GymDiff) -> Result<()> {
+        let sheet_id = self.config.gym_sheet_id(diff.gym_id);
+        let columns = self.config.gym_columns(diff.gym_id);
+        // Archive first so that removed rows are not lost if something fails after
+        if let Some(archive) = &diff.archive {
+            info!(
+                archive.sheet_name,
+                count = archive.rows.len(),
+                "archiving rows"
+            );
+            // Rows are appended from the header's first non-empty cell, which is after the
+            // untitled columns
+            let untitled_count = untitled_column_count(columns);
+            if !self.has_sheet(diff.gym_id, &archive.sheet_name) {
+                info!(archive.sheet_name, "creating archive sheet");
+                let sheet_id_num =
+                    sheets::add_sheet(&self.sheet_client, sheet_id, &archive.sheet_name).await?;
+                let header = columns[untitled_count..]
+                    .iter()
+                    .map(|c| c.title())
+                    .chain(diff::ARCHIVE_COLUMN_TITLES)
+                    .collect::<Vec<_>>();
+                sheets::batch_update(
                     &self.sheet_client,
-                    &self.sheet_id,
-                    Some(sheets::color_from_hex(
-                        &self.config.new_climb_background_color,
-                    )),
-                    GridRange {
-                        sheet_id: Some(sheet_id_num),
-                        start_row_index: Some(1),
-                        // +2 because have to account account for header row.
-                        // Index starts from first non-header row.
-                        end_row_index: Some(last_new_route_idx as i32 + 2),
-                        start_column_index: Some(self.config.date_column_idx),
-                        end_column_index: Some(self.config.date_column_idx + 1),
-                    },
+                    sheet_id,
+                    vec![sheets::header_request(sheet_id_num, 0, &header)],
                 )
                 .await?;
             }
+            sheets::append_rows(
+                &self.sheet_client,
+                sheet_id,
+                &archive.sheet_name,
+                archive
+                    .rows
+                    .iter()
+                    .map(|row| row[untitled_count.min(row.len())..].to_vec())
+                    .collect(),
+            )
+            .await?;
+        }
+
+        // All changes to a sheet are sent in one batch to keep the number of requests in check
+        for sheet_diff in &diff.sheets {
+            let sheet_id_num = sheet_diff.sheet_id_num;
+            let first_column_idx = sheet_diff.first_column_idx;
+            let mut requests = sheet_diff
+                .updated_rows
+                .iter()
+                .flat_map(|update| {
+                    update.changes.iter().map(|change| {
+                        let column = &columns[change.column];
+                        sheets::update_cell_request(
+                            sheet_id_num,
+                            update.row_idx,
+                            first_column_idx + change.column as i32,
+                            column.sheet_cell(&update.climb),
+                            // Leave background of other cells alone, so that the highlight of new
+                            // climbs is not removed for example
+                            column.has_color(),
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            requests.extend(sheets::delete_rows_requests(
+                sheet_id_num,
+                sheet_diff.removed_rows.iter().map(|r| r.row_idx).collect(),
+            ));
+
+            if !sheet_diff.new_climbs.is_empty() {
+                let rows = sheet_diff
+                    .new_climbs
+                    .iter()
+                    .map(|climb| Self::climb_to_cells(climb, columns, first_column_idx))
+                    .collect();
+                requests.push(sheets::append_cells_request(sheet_id_num, rows));
+            }
+
+            if !sheet_diff.sort_column_idxs.is_empty() {
+                requests.push(sheets::sort_by_columns_request(
+                    sheet_id_num,
+                    &sheet_diff.sort_column_idxs,
+                ));
+            }
+
+            requests.extend(self.highlight_requests(
+                diff.gym_id,
+                sheet_id_num,
+                first_column_idx,
+                &sheet_diff.new_row_idxs,
+            ));
+            requests.extend(hide_columns_requests(
+                sheet_id_num,
+                first_column_idx,
+                columns,
+            ));
+
+            info!(
+                sheet_diff.sheet_name,
+                request_count = requests.len(),
+                "updating sheet"
+            );
+            sheets::batch_update(&self.sheet_client, sheet_id, requests).await?;
         }
 
         Ok(())
     }
 
-    pub async fn get_gym_sheets(&self, gym: &vertical_life::Gym) -> Result<Vec<&Sheet>> {
-        let gym_location_name = parse_location_from_gym_name(&gym.name);
-        Ok(self
-            .spreadsheet
-            .sheets
-            .as_ref()
-            .map(|sheets| {
-                sheets
-                    .iter()
-                    .filter(|s| {
-                        s.properties
-                            .as_ref()
-                            .and_then(|p| {
-                                p.title.as_ref().map(|t| t.starts_with(&gym_location_name))
-                            })
-                            .unwrap_or(false)
-                    })
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_else(Vec::new))
+    /// Create sheets by copying the configured template sheet, or if there is none, by adding an
+    /// empty sheet with a header row. The cached spreadsheet is refreshed to include the new
+    /// sheets.
+    async fn create_sheets(
+        &mut self,
+        gym: &vertical_life::Gym,
+        sheet_names: &[String],
+    ) -> Result<()> {
+        if sheet_names.is_empty() {
+            return Ok(());
+        }
+        let sheet_id = self.config.gym_sheet_id(gym.id);
+
+        let template_sheet_id_num = match &self.config.template_sheet_name {
+            Some(template_sheet_name) => {
+                let sheet = self.find_sheet(sheet_id, template_sheet_name).ok_or_else(|| {
+                    eyre::eyre!("template sheet '{template_sheet_name}' not found in spreadsheet {sheet_id}")
+                })?;
+                sheet.properties.as_ref().and_then(|p| p.sheet_id)
+            }
+            None => None,
+        };
+
+        for sheet_name in sheet_names {
+            match template_sheet_id_num {
+                Some(template_sheet_id_num) => {
+                    info!(sheet_name, "creating sheet from template");
+                    sheets::duplicate_sheet(
+                        &self.sheet_client,
+                        sheet_id,
+                        template_sheet_id_num,
+                        sheet_name,
+                    )
+                    .await?;
+                }
+                None => {
+                    info!(sheet_name, "creating sheet");
+                    let sheet_id_num =
+                        sheets::add_sheet(&self.sheet_client, sheet_id, sheet_name).await?;
+                    let columns = self.config.gym_columns(gym.id);
+                    let header = columns.iter().map(|c| c.title()).collect::<Vec<_>>();
+                    let mut requests = vec![sheets::header_request(sheet_id_num, 0, &header)];
+                    requests.extend(hide_columns_requests(sheet_id_num, 0, columns));
+                    sheets::batch_update(&self.sheet_client, sheet_id, requests).await?;
+                }
+            }
+        }
+
+        self.refresh_spreadsheet(sheet_id).await
+    }
+
+    /// Append a row to the changelog tab, creating the tab with a header first if it's missing
+    async fn append_changelog_row(&mut self, row: Row) -> Result<()> {
+        let sheet_id = &self.config.sheet_id;
+        let sheet_name = &self.config.changelog_sheet_name;
+        if self.find_sheet(sheet_id, sheet_name).is_none() {
+            info!(sheet_name, "creating changelog sheet");
+            let sheet_id_num = sheets::add_sheet(&self.sheet_client, sheet_id, sheet_name).await?;
+            sheets::batch_update(
+                &self.sheet_client,
+                sheet_id,
+                vec![sheets::header_request(
+                    sheet_id_num,
+                    0,
+                    &diff::CHANGELOG_HEADER,
+                )],
+            )
+            .await?;
+            self.refresh_spreadsheet(sheet_id).await?;
+        }
+        sheets::append_row(&self.sheet_client, sheet_id, sheet_name, row).await?;
+        Ok(())
+    }
+
+    async fn write_report(&mut self, sheet_name: &str, header: Row, rows: Vec<Row>) -> Result<()> {
+        let sheet_id = &self.config.sheet_id;
+        let existing_sheet_id_num = self
+            .find_sheet(sheet_id, sheet_name)
+            .and_then(|s| s.properties.as_ref())
+            .and_then(|p| p.sheet_id);
+        let sheet_id_num = match existing_sheet_id_num {
+            Some(sheet_id_num) => sheet_id_num,
+            None => {
+                info!(sheet_name, "creating report sheet");
+                let sheet_id_num =
+                    sheets::add_sheet(&self.sheet_client, sheet_id, sheet_name).await?;
+                self.refresh_spreadsheet(sheet_id).await?;
+                sheet_id_num
+            }
+        };
+        info!(sheet_name, row_count = rows.len(), "writing report");
+        let header_request = sheets::header_request(
+            sheet_id_num,
+            0,
+            &header.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+        let values = std::iter::once(header).chain(rows).collect();
+        sheets::replace_rows(&self.sheet_client, sheet_id, sheet_name, values).await?;
+        // Rewrites the header to make it bold
+        sheets::batch_update(&self.sheet_client, sheet_id, vec![header_request]).await?;
+        Ok(())
     }
 }
 
-/// Returns for example "Ristikko - Reitit"
-fn format_sheet_name(gym_location_name: &str, plural_human_item_type: &str) -> String {
-    format!("{} - {}", gym_location_name, plural_human_item_type)
+/// Zero indexed column where the table starts. Untitled columns, like the color swatch, are in
+/// front of the header's first non-empty cell.
+fn table_start(header: &[CellData], columns: &[Column]) -> i32 {
+    header
+        .iter()
+        .position(|c| !sheets::cell_formatted_value(c).is_empty())
+        .and_then(|idx| idx.checked_sub(untitled_column_count(columns)))
+        .unwrap_or(0) as i32
 }
 
-fn wall_category_to_plural_human_type(wall_category: &str) -> String {
-    match wall_category {
-        "gym_bouldering" => "Boulderit",
-        "gym_sportclimbing" => "Reitit",
-        _ => panic!("unknown wall category: {}", wall_category),
-    }
-    .to_string()
+/// Hide the columns that are only meant for climbsheet itself, like the climb id
+fn hide_columns_requests(
+    sheet_id_num: i32,
+    first_column_idx: i32,
+    columns: &[Column],
+) -> Vec<Request> {
+    columns
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| c.field.is_hidden())
+        .map(|(idx, _)| sheets::hide_column_request(sheet_id_num, first_column_idx + idx as i32))
+        .collect()
 }
 
-/// With input "Kiipeilyareena Ristikko" this should return "Ristikko"
-fn parse_location_from_gym_name(gym_name: &str) -> &str {
-    gym_name.split(' ').nth(1).unwrap()
+/// Background color of cell as hex, or empty if it has none
+fn cell_background_color(cell: &CellData) -> String {
+    cell.user_entered_format
+        .as_ref()
+        .and_then(|f| f.background_color.as_ref())
+        .map(sheets::color_to_hex)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(value: &str) -> CellData {
+        CellData {
+            formatted_value: Some(value.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn converted_difficulty_test() {
+        let column = Column {
+            field: ColumnField::ConvertedDifficulty,
+            title: None,
+            format: None,
+            color: false,
+        };
+        let convert = |difficulty: &str, item_type: &str| {
+            column.sheet_value(&vertical_life::Climb {
+                difficulty: difficulty.to_string(),
+                item_type: item_type.to_string(),
+                ..vertical_life::Climb::test(1)
+            })
+        };
+        assert_eq!(convert("6B+", "gym_boulder"), "V4");
+        assert_eq!(convert("V4", "gym_boulder"), "6B/6B+");
+        assert_eq!(convert("6a+", "gym_route"), "5.10b");
+        assert_eq!(convert("5.10a", "gym_route"), "6a");
+        assert_eq!(convert("7", "gym_route"), "6b+");
+        assert_eq!(convert("Keltainen", "gym_boulder"), "");
+    }
+
+    #[test]
+    fn color_column_first_test() {
+        let columns = config::default_columns();
+        let climb = vertical_life::Climb::test(1);
+        // The color swatch is in column A in front of the header, and the table's columns begin
+        // from column B
+        let header = ["", "Nimi", "Vaikeus", "Päivämäärä"].map(cell);
+        let first_column_idx = table_start(&header, &columns);
+        assert_eq!(first_column_idx, 0);
+        assert_eq!(table_start(&header, &columns[1..]), 1);
+
+        // The cells as they are read back from the sheet
+        let cells = ClimbSheet::climb_to_cells(&climb, &columns, first_column_idx)
+            .into_iter()
+            .zip(&columns)
+            .map(|(c, column)| match column.field {
+                ColumnField::ShareUrl => CellData {
+                    hyperlink: Some(climb.share_url.clone()),
+                    ..cell(column.format())
+                },
+                _ => CellData {
+                    user_entered_format: c.user_entered_format,
+                    ..cell(&column.sheet_value(&climb))
+                },
+            })
+            .collect::<Vec<_>>();
+        let row = GymSheetRow::new(
+            "Ristikko - Boulderit",
+            0,
+            1,
+            cells.iter().map(sheets::cell_user_entered_value).collect(),
+            cells.iter().map(cell_background_color).collect(),
+            &columns,
+        );
+        assert_eq!(row.colors[0], climb.color);
+        assert_eq!(
+            row.values,
+            columns
+                .iter()
+                .map(|c| c.sheet_value(&climb))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn to_archive_row_test() {
+        let values = ["", "A1", "6A", "1.2.2023", "Matti", "Seinä 1"];
+        let mut cells = values.iter().map(|v| cell(v)).collect::<Vec<_>>();
+        cells.push(CellData {
+            hyperlink: Some("https://example.com".to_string()),
+            ..cell("🔗")
+        });
+        let columns = config::default_columns();
+        let row = GymSheetRow::new(
+            "Ristikko - Boulderit",
+            0,
+            1,
+            cells.iter().map(sheets::cell_user_entered_value).collect(),
+            cells.iter().map(cell_background_color).collect(),
+            &columns,
+        );
+        assert_eq!(row.climb_id, None);
+        let removed_at = chrono::NaiveDate::from_ymd_opt(2023, 3, 3).unwrap();
+        assert_eq!(
+            row.to_archive_row(removed_at, columns.len()),
+            vec![
+                "",
+                "A1",
+                "6A",
+                "1.2.2023",
+                "Matti",
+                "Seinä 1",
+                r#"=HYPERLINK("https://example.com"; "🔗")"#,
+                "",
+                "3.3.2023",
+                "30",
+            ]
+        );
+    }
 }
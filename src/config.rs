@@ -1,37 +1,813 @@
 use secrecy::Secret;
-use serde::Deserialize;
-use std::{env, path::PathBuf};
+use serde::{Deserialize, Deserializer};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+};
 use tracing::error;
 
+use crate::{grades::GradeSystem, vertical_life};
+
 const CONFIG_PATH_ENV: &str = "CONFIG_PATH";
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     /// Path to service account credentials that have access to spreadsheet that sheet_id points
     /// to. To give service account an access to spreadsheet you need to share it to the service
-    /// account email like you would share it to a real user.
+    /// account email like you would share it to a real user. Not needed for export-html.
+    #[serde(default)]
     pub service_account_credentials_path: PathBuf,
-    /// Spreadsheet id you can get from the browser URL
+    /// Spreadsheet id you can get from the browser URL. Not needed for export-html.
+    #[serde(default)]
     pub sheet_id: String,
     pub vertical_life_email: String,
     pub vertical_life_password: Secret<String>,
     /// Vertical life gym ids that should be fetched. Spreadsheet should have matching sheet(s)
     /// with name for example 'Ristikko - Reitit'. The program will be able to tell that for gym
-    /// id 2108 it needs to climbs to that tab (or the bouldering equivalent).
-    pub gyms: Vec<u32>,
-    pub climb_color_column_idx: i32,
-    pub grade_column_idx: i32,
-    pub date_column_idx: i32,
+    /// id 2108 it needs to climbs to that tab (or the bouldering equivalent). Gyms whose tabs
+    /// can't be told from their name can be given as a table instead, see GymConfig.
+    #[serde(deserialize_with = "deserialize_gyms")]
+    pub gyms: Vec<GymConfig>,
+    /// Columns of gym's sheets in order, starting from the header's first non-empty column, or
+    /// from the color swatch in front of it. Used both when writing rows and when reading them
+    /// back. Defaults to the hold color swatch, label, grade, date, setter, wall, link and id.
+    #[serde(default = "default_columns")]
+    pub columns: Vec<Column>,
+    /// Replaced by columns, only read to tell that they are no longer supported
+    #[serde(default)]
+    climb_color_column_idx: Option<i32>,
+    #[serde(default)]
+    grade_column_idx: Option<i32>,
+    #[serde(default)]
+    date_column_idx: Option<i32>,
+    /// Suffix of the gym's tab for each Vertical Life wall category, for example
+    /// "gym_bouldering" = "Boulderit" puts Ristikko's boulders to 'Ristikko - Boulderit'. Several
+    /// categories can share a tab. Walls with a category that is not listed are skipped.
+    /// Defaults to "Boulderit" and "Reitit".
+    #[serde(default = "default_wall_categories")]
+    pub wall_categories: HashMap<String, String>,
     pub new_climb_background_color: String,
+    /// Climbs set within this many days are highlighted as new
+    #[serde(default = "default_new_climb_days")]
+    pub new_climb_days: i64,
+    /// When set, climbs removed from Vertical Life are moved to gym's archive tab instead of just
+    /// being deleted. For example with "Arkisto", Ristikko's removed climbs go to
+    /// 'Ristikko - Arkisto'.
+    pub archive_sheet_suffix: Option<String>,
+    /// Where climbs are synced to, the Google spreadsheet by default
+    #[serde(default)]
+    pub output: Output,
+    /// Tab that gets a row for each gym on every run, telling what changed. Created when missing.
+    #[serde(default = "default_changelog_sheet_name")]
+    pub changelog_sheet_name: String,
+    /// Tab with the number of climbs per grade for each gym tab, sector and hold color. Rewritten
+    /// on every run and created when missing.
+    #[serde(default = "default_stats_sheet_name")]
+    pub stats_sheet_name: String,
+    /// Tab with each setter's climb count, grades and climbs set per month for each gym.
+    /// Rewritten on every run and created when missing.
+    #[serde(default = "default_setters_sheet_name")]
+    pub setters_sheet_name: String,
+    /// Targets that get the new and removed climbs after a sync, see Webhook
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// When set, an email digest of the new climbs is sent after syncs that added climbs
+    pub email: Option<EmailConfig>,
+    /// When set, Atom feeds of recently set climbs are written on every run
+    pub feeds: Option<FeedConfig>,
+    /// Sheet that is copied when a gym's sheet is missing from the spreadsheet, for example
+    /// 'Pohja'. It should have the header and formatting but no rows. When not set, missing
+    /// sheets are created with just a header row.
+    pub template_sheet_name: Option<String>,
+    /// Order of the climbs in gym's sheets. Sorting by grade needs a grade_order column.
+    #[serde(default)]
+    pub sort_by: SortBy,
+    /// SQLite database that every climb seen is recorded to, for example
+    /// '/data/history.sqlite'. Created when missing. See history::History.
+    pub history_db_path: Option<PathBuf>,
+}
+
+/// Order of the rows in gym's sheets, hardest or newest first
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Date,
+    /// By grade, see grades::Grade, and climbs of the same grade by date
+    Grade,
+}
+
+/// Output that climbs are synced to. For files, each tab is a file of its own in directory, for
+/// example:
+///
+/// ```toml
+/// [output]
+/// type = "csv"
+/// directory = "climbs"
+/// ```
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Output {
+    /// The spreadsheet that sheet_id points to
+    #[default]
+    Sheets,
+    Csv {
+        directory: PathBuf,
+    },
+    Json {
+        directory: PathBuf,
+    },
+}
+
+/// Gym to fetch, either just the Vertical Life gym id, or a table that also tells which tabs the
+/// gym's climbs go to and overrides settings for the gym. Settings that are not overridden are
+/// the global ones.
+///
+/// ```toml
+/// gyms = [
+///     2108,
+///     { id = 3456, tab_prefix = "Kivi Vallila", tabs = { gym_sportclimbing = "Vallila" } },
+///     { id = 4567, wall_categories = { gym_bouldering = "Boulderit" }, new_climb_days = 14 },
+///     { id = 5678, sheet_id = "1AbC..." },
+/// ]
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GymConfig {
+    pub id: u32,
+    /// Prefix of the gym's tabs, for example "Ristikko" for 'Ristikko - Reitit'. When not set,
+    /// it's the second word of a two word gym name, like "Kiipeilyareena Ristikko".
+    pub tab_prefix: Option<String>,
+    /// Exact tab names by wall category, overriding the prefix and the suffix in wall_categories
+    #[serde(default)]
+    pub tabs: HashMap<String, String>,
+    /// Spreadsheet that gym's tabs and archive are in, instead of Config::sheet_id. The changelog
+    /// and the report tabs stay in the main spreadsheet.
+    pub sheet_id: Option<String>,
+    /// Wall categories to sync and their tab suffixes, instead of Config::wall_categories
+    pub wall_categories: Option<HashMap<String, String>>,
+    /// Columns of the gym's sheets, instead of Config::columns
+    pub columns: Option<Vec<Column>>,
+    pub new_climb_background_color: Option<String>,
+    pub new_climb_days: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum GymConfigEntry {
+    Id(u32),
+    Table(Box<GymConfig>),
+}
+
+/// Gyms are listed as bare ids or as tables with the id and overrides
+fn deserialize_gyms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<GymConfig>, D::Error> {
+    let entries = Vec::<GymConfigEntry>::deserialize(deserializer)?;
+    Ok(entries
+        .into_iter()
+        .map(|entry| match entry {
+            GymConfigEntry::Id(id) => GymConfig::from(id),
+            GymConfigEntry::Table(gym) => *gym,
+        })
+        .collect())
+}
+
+impl From<u32> for GymConfig {
+    fn from(id: u32) -> Self {
+        GymConfig {
+            id,
+            tab_prefix: None,
+            tabs: HashMap::new(),
+            sheet_id: None,
+            wall_categories: None,
+            columns: None,
+            new_climb_background_color: None,
+            new_climb_days: None,
+        }
+    }
+}
+
+impl Config {
+    /// Gym's entry in config, or the defaults for gyms that are not configured
+    pub fn gym_config(&self, gym: &vertical_life::Gym) -> GymConfig {
+        self.gym_entry(gym.id)
+            .cloned()
+            .unwrap_or_else(|| GymConfig::from(gym.id))
+    }
+
+    fn gym_entry(&self, gym_id: u32) -> Option<&GymConfig> {
+        self.gyms.iter().find(|g| g.id == gym_id)
+    }
+
+    /// Spreadsheet of gym's tabs, the gym's own if it has one
+    pub fn gym_sheet_id(&self, gym_id: u32) -> &str {
+        self.gym_entry(gym_id)
+            .and_then(|g| g.sheet_id.as_deref())
+            .unwrap_or(&self.sheet_id)
+    }
+
+    /// All the spreadsheets that are synced to, the main one first
+    pub fn sheet_ids(&self) -> Vec<&str> {
+        let mut sheet_ids = vec![self.sheet_id.as_str()];
+        for gym in &self.gyms {
+            let sheet_id = self.gym_sheet_id(gym.id);
+            if !sheet_ids.contains(&sheet_id) {
+                sheet_ids.push(sheet_id);
+            }
+        }
+        sheet_ids
+    }
+
+    /// Columns of gym's sheets, the gym's own if it overrides them
+    pub fn gym_columns(&self, gym_id: u32) -> &[Column] {
+        self.gym_entry(gym_id)
+            .and_then(|g| g.columns.as_deref())
+            .unwrap_or(&self.columns)
+    }
+
+    pub fn gym_wall_categories(&self, gym_id: u32) -> &HashMap<String, String> {
+        self.gym_entry(gym_id)
+            .and_then(|g| g.wall_categories.as_ref())
+            .unwrap_or(&self.wall_categories)
+    }
+
+    pub fn gym_new_climb_background_color(&self, gym_id: u32) -> &str {
+        self.gym_entry(gym_id)
+            .and_then(|g| g.new_climb_background_color.as_deref())
+            .unwrap_or(&self.new_climb_background_color)
+    }
+
+    pub fn gym_new_climb_days(&self, gym_id: u32) -> i64 {
+        self.gym_entry(gym_id)
+            .and_then(|g| g.new_climb_days)
+            .unwrap_or(self.new_climb_days)
+    }
+
+    /// Prefix of gym's tabs, for example "Ristikko" for gym "Kiipeilyareena Ristikko". Fails if
+    /// the prefix is not configured and can't be told from the gym's name, or if it's configured
+    /// as the prefix of another gym.
+    pub fn tab_prefix(&self, gym: &vertical_life::Gym) -> eyre::Result<String> {
+        if let Some(tab_prefix) = self.gym_config(gym).tab_prefix {
+            return Ok(tab_prefix);
+        }
+        let tab_prefix = parse_location_from_gym_name(&gym.name).ok_or_else(|| {
+            eyre::eyre!(
+                "can't tell tab prefix of gym '{}' from its name, set tab_prefix for gym {} in config",
+                gym.name,
+                gym.id
+            )
+        })?;
+        if let Some(other_gym) = self
+            .gyms
+            .iter()
+            .find(|g| g.id != gym.id && g.tab_prefix.as_deref() == Some(tab_prefix))
+        {
+            eyre::bail!(
+                "tab prefix '{tab_prefix}' of gym '{}' is also the tab_prefix of gym {}, set tab_prefix for gym {} in config",
+                gym.name,
+                other_gym.id,
+                gym.id
+            );
+        }
+        Ok(tab_prefix.to_string())
+    }
+
+    /// Returns the name of gym's sheet for wall category, for example "Ristikko - Boulderit" for
+    /// gym "Kiipeilyareena Ristikko" and wall category "gym_bouldering", or None if the wall
+    /// category is not configured
+    pub fn gym_sheet_name(
+        &self,
+        gym: &vertical_life::Gym,
+        wall_category: &str,
+    ) -> eyre::Result<Option<String>> {
+        if let Some(tab) = self.gym_config(gym).tabs.get(wall_category) {
+            return Ok(Some(tab.clone()));
+        }
+        match self.gym_wall_categories(gym.id).get(wall_category) {
+            Some(suffix) => Ok(Some(format_sheet_name(&self.tab_prefix(gym)?, suffix))),
+            None => Ok(None),
+        }
+    }
+
+    /// Names of gym's sheets for all configured wall categories
+    pub fn gym_sheet_names(&self, gym: &vertical_life::Gym) -> eyre::Result<Vec<String>> {
+        let gym_config = self.gym_config(gym);
+        let mut sheet_names = self
+            .gym_wall_categories(gym.id)
+            .keys()
+            .chain(gym_config.tabs.keys())
+            .filter_map(|category| self.gym_sheet_name(gym, category).transpose())
+            .collect::<eyre::Result<Vec<_>>>()?;
+        sheet_names.sort();
+        sheet_names.dedup();
+        Ok(sheet_names)
+    }
+
+    /// Check that no two gyms resolve to the same tab, including the archive tabs. Gyms sharing a
+    /// tab would remove each other's rows on every run. Tab names can come from gym names, so
+    /// this needs the gym details and is done once per run, after they are fetched.
+    pub fn check_sheet_names<'g>(
+        &self,
+        gyms: impl IntoIterator<Item = &'g vertical_life::Gym>,
+    ) -> eyre::Result<()> {
+        let mut sheet_gyms: HashMap<(&str, String), &vertical_life::Gym> = HashMap::new();
+        for gym in gyms {
+            let mut sheet_names = self.gym_sheet_names(gym)?;
+            if let Some(archive_sheet_suffix) = &self.archive_sheet_suffix {
+                sheet_names.push(format_sheet_name(
+                    &self.tab_prefix(gym)?,
+                    archive_sheet_suffix,
+                ));
+            }
+            for sheet_name in sheet_names {
+                let key = (self.gym_sheet_id(gym.id), sheet_name);
+                if let Some(other_gym) = sheet_gyms.insert(key.clone(), gym) {
+                    if other_gym.id != gym.id {
+                        eyre::bail!(
+                            "gyms '{}' ({}) and '{}' ({}) both use tab '{}', set tab_prefix or tabs for one of them in config",
+                            other_gym.name,
+                            other_gym.id,
+                            gym.name,
+                            gym.id,
+                            key.1
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that gyms can't end up writing to each other's tabs and that settings that are no
+    /// longer supported are not used
+    fn validate(&self) -> Result<(), String> {
+        if self.climb_color_column_idx.is_some()
+            || self.grade_column_idx.is_some()
+            || self.date_column_idx.is_some()
+        {
+            return Err(
+                "climb_color_column_idx, grade_column_idx and date_column_idx are no \
+                 longer supported, list the sheet's columns in [[columns]] instead, with \
+                 field = \"color\" for the hold color swatch"
+                    .to_string(),
+            );
+        }
+        let mut ids = HashSet::new();
+        let mut tab_prefixes = HashSet::new();
+        let mut tabs = HashMap::new();
+        for gym in &self.gyms {
+            if !ids.insert(gym.id) {
+                return Err(format!("gym {} is listed more than once", gym.id));
+            }
+            if let Some(tab_prefix) = &gym.tab_prefix {
+                if !tab_prefixes.insert(tab_prefix) {
+                    return Err(format!("tab_prefix '{tab_prefix}' is used by several gyms"));
+                }
+            }
+            for tab in gym.tabs.values() {
+                if let Some(other_id) = tabs.insert(tab, gym.id) {
+                    if other_id != gym.id {
+                        return Err(format!(
+                            "tab '{tab}' is used by both gym {other_id} and gym {}",
+                            gym.id
+                        ));
+                    }
+                }
+            }
+        }
+        let column_lists = std::iter::once(&self.columns)
+            .chain(self.gyms.iter().filter_map(|g| g.columns.as_ref()));
+        for columns in column_lists {
+            for column in columns {
+                if column.field != ColumnField::ConvertedDifficulty {
+                    continue;
+                }
+                for name in column.format().split(',') {
+                    if GradeSystem::from_name(name.trim()).is_none() {
+                        return Err(format!(
+                            "unknown grade system '{name}' in converted_difficulty"
+                        ));
+                    }
+                }
+            }
+            if self.sort_by == SortBy::Grade
+                && column_position(columns, ColumnField::GradeOrder).is_none()
+            {
+                return Err("sort_by = \"grade\" needs a grade_order column".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Webhook target that gets the new and removed climbs of each gym after a sync, for example:
+///
+/// ```toml
+/// [[webhooks]]
+/// url = "https://hooks.slack.com/services/..."
+/// format = "slack"
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct Webhook {
+    pub url: Secret<String>,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookFormat {
+    /// The climbs as JSON, see notify::GymNotification
+    #[default]
+    Json,
+    /// Message for a Slack incoming webhook
+    Slack,
+    /// Message for a Discord webhook
+    Discord,
+}
+
+/// SMTP settings and recipients of the email digest, for example:
+///
+/// ```toml
+/// [email]
+/// smtp_host = "smtp.example.com"
+/// username = "climbsheet@example.com"
+/// password = "..."
+/// from = "Climbsheet <climbsheet@example.com>"
+/// to = ["member@example.com"]
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    /// Port of the SMTP server, which must support STARTTLS. Defaults to 587.
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+    pub username: String,
+    pub password: Secret<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Atom feeds of recently set climbs, one for each gym and a combined "all.xml", for example:
+///
+/// ```toml
+/// [feeds]
+/// directory = "/data/feeds"
+/// days = 14
+/// ```
+#[derive(Deserialize, Debug, Clone)]
+pub struct FeedConfig {
+    /// Directory the feed files are written to
+    pub directory: PathBuf,
+    /// Climbs set within this many days are included. Defaults to 30.
+    #[serde(default = "default_feed_days")]
+    pub days: i64,
+}
+
+fn default_feed_days() -> i64 {
+    30
+}
+
+/// A column of gym's sheets, for example:
+///
+/// ```toml
+/// [[columns]]
+/// field = "set_at"
+/// format = "%d.%m.%Y"
+/// ```
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    pub field: ColumnField,
+    /// Header of the column when a sheet is created without a template. Defaults to a Finnish
+    /// name of the field.
+    pub title: Option<String>,
+    /// For set_at, a chrono format string for the date, defaults to "%-d.%-m.%Y". For share_url,
+    /// the text of the link, defaults to "🔗". For converted_difficulty, the grade systems to
+    /// convert to, see ColumnField::ConvertedDifficulty.
+    pub format: Option<String>,
+    /// Set cell's background to the climb's hold color
+    #[serde(default)]
+    pub color: bool,
+}
+
+/// Climb fields that can be shown in a column. Names match to the fields of
+/// vertical_life::Climb.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnField {
+    /// Empty cell with the climb's hold color as the background. Its header is empty by default,
+    /// so it can be a swatch in front of the table.
+    Color,
+    RouteCardLabel,
+    Difficulty,
+    SetAt,
+    RouteSetter,
+    ParentName,
+    SectorName,
+    ShareUrl,
+    /// Vertical Life id of the climb, which is used to match rows to climbs. The column is hidden.
+    Id,
+    /// The grade converted to another system, for example "V4" for "6B+". Column's format lists
+    /// the systems to convert to, separated by commas, out of "font", "v", "french", "uiaa" and
+    /// "yds". Each climb's grade is converted to the first listed system of its discipline that
+    /// is not the grade's own system. Defaults to "v,font,french,yds", which converts
+    /// Fontainebleau and V-scale to each other, French to YDS and YDS and UIAA to French.
+    ConvertedDifficulty,
+    /// Number that orders the climbs by grade, see grades::Grade. Empty for unknown grades. The
+    /// column is hidden.
+    GradeOrder,
+}
+
+impl ColumnField {
+    /// Whether the column is only meant for climbsheet itself and hidden from people
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, Self::Id | Self::GradeOrder)
+    }
+}
+
+impl Column {
+    fn new(field: ColumnField) -> Self {
+        Self {
+            field,
+            title: None,
+            format: None,
+            color: false,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.title.as_deref().unwrap_or(match self.field {
+            ColumnField::Color => "",
+            ColumnField::RouteCardLabel => "Nimi",
+            ColumnField::Difficulty => "Vaikeus",
+            ColumnField::SetAt => "Päivämäärä",
+            ColumnField::RouteSetter => "Rakentaja",
+            ColumnField::ParentName => "Seinä",
+            ColumnField::SectorName => "Sektori",
+            ColumnField::ShareUrl => "Linkki",
+            ColumnField::Id => "Id",
+            ColumnField::GradeOrder => "Vaikeusjärjestys",
+            ColumnField::ConvertedDifficulty => "Muunnettu vaikeus",
+        })
+    }
+
+    pub fn format(&self) -> &str {
+        self.format.as_deref().unwrap_or(match self.field {
+            ColumnField::SetAt => "%-d.%-m.%Y",
+            ColumnField::ShareUrl => "🔗",
+            ColumnField::ConvertedDifficulty => "v,font,french,yds",
+            _ => "",
+        })
+    }
+
+    /// Whether cell's background is set to the climb's hold color
+    pub fn has_color(&self) -> bool {
+        self.color || self.field == ColumnField::Color
+    }
+
+    /// For converted_difficulty, the systems listed in format. Unknown names are skipped, see
+    /// Config::validate.
+    pub fn grade_systems(&self) -> Vec<GradeSystem> {
+        self.format()
+            .split(',')
+            .filter_map(|name| GradeSystem::from_name(name.trim()))
+            .collect()
+    }
+}
+
+/// Returns the position of the first column with field
+pub fn column_position(columns: &[Column], field: ColumnField) -> Option<usize> {
+    columns.iter().position(|c| c.field == field)
+}
+
+/// Number of columns at the start that have an empty header, like the color swatch. The header's
+/// first non-empty cell is where the columns after them begin.
+pub fn untitled_column_count(columns: &[Column]) -> usize {
+    columns.iter().take_while(|c| c.title().is_empty()).count()
+}
+
+/// The layout of the sheets before columns were configurable: the hold color swatch in front of
+/// the table, then label, grade, date, setter, wall and link, and the id column after them
+pub fn default_columns() -> Vec<Column> {
+    vec![
+        Column::new(ColumnField::Color),
+        Column::new(ColumnField::RouteCardLabel),
+        Column::new(ColumnField::Difficulty),
+        Column::new(ColumnField::SetAt),
+        Column::new(ColumnField::RouteSetter),
+        Column::new(ColumnField::ParentName),
+        Column::new(ColumnField::ShareUrl),
+        Column::new(ColumnField::Id),
+    ]
+}
+
+fn default_changelog_sheet_name() -> String {
+    "Muutokset".to_string()
+}
+
+fn default_stats_sheet_name() -> String {
+    "Tilastot".to_string()
+}
+
+fn default_setters_sheet_name() -> String {
+    "Rakentajat".to_string()
+}
+
+fn default_new_climb_days() -> i64 {
+    7
+}
+
+fn default_wall_categories() -> HashMap<String, String> {
+    HashMap::from([
+        ("gym_bouldering".to_string(), "Boulderit".to_string()),
+        ("gym_sportclimbing".to_string(), "Reitit".to_string()),
+    ])
+}
+
+/// Returns for example "Ristikko - Reitit"
+pub fn format_sheet_name(gym_location_name: &str, plural_human_item_type: &str) -> String {
+    format!("{} - {}", gym_location_name, plural_human_item_type)
+}
+
+/// With input "Kiipeilyareena Ristikko" this should return "Ristikko". Names with more or less
+/// than two words are ambiguous, for example "Boulderkeskus Kivi Vallila", and return None.
+fn parse_location_from_gym_name(gym_name: &str) -> Option<&str> {
+    match gym_name.split_whitespace().collect::<Vec<_>>()[..] {
+        [_, location] => Some(location),
+        _ => None,
+    }
 }
 
 pub fn read_config() -> Config {
     env::var(CONFIG_PATH_ENV)
         .map_err(|_| format!("{CONFIG_PATH_ENV} environment variable not set"))
         .and_then(|config_path| std::fs::read_to_string(config_path).map_err(|e| e.to_string()))
-        .and_then(|str| toml::from_str(&str).map_err(|e| e.to_string()))
+        .and_then(|str| toml::from_str::<Config>(&str).map_err(|e| e.to_string()))
+        .and_then(|config| config.validate().map(|_| config))
         .unwrap_or_else(|err| {
             error!("failed to read config: {err}");
             std::process::exit(1);
         })
 }
+
+#[cfg(test)]
+impl Config {
+    /// Config for tests with gym 2108 and the defaults for everything that has one
+    pub(crate) fn test() -> Self {
+        toml::from_str(
+            r##"
+            vertical_life_email = "test@example.com"
+            vertical_life_password = "password"
+            gyms = [2108]
+            new_climb_background_color = "#00ff00"
+            "##,
+        )
+        .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Gyms {
+        #[serde(deserialize_with = "deserialize_gyms")]
+        gyms: Vec<GymConfig>,
+    }
+
+    #[test]
+    fn check_sheet_names_test() {
+        let gym = |id, name: &str| vertical_life::Gym {
+            id,
+            name: name.to_string(),
+            boulder_count: 0,
+            route_count: 0,
+            gym_sectors: vec![],
+        };
+        let config = |gyms: &str| -> Config {
+            toml::from_str(&format!(
+                r##"
+                vertical_life_email = "test@example.com"
+                vertical_life_password = "password"
+                new_climb_background_color = "#00ff00"
+                gyms = {gyms}
+                "##
+            ))
+            .unwrap()
+        };
+        let ristikko = gym(2108, "Kiipeilyareena Ristikko");
+        let other_ristikko = gym(3456, "Boulderpaja Ristikko");
+
+        // Neither prefix is configured, but both are taken from the names
+        let err = config("[2108, 3456]")
+            .check_sheet_names([&ristikko, &other_ristikko])
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("both use tab 'Ristikko - "),
+            "{err}"
+        );
+        config(r#"[2108, { id = 3456, tab_prefix = "Paja" }]"#)
+            .check_sheet_names([&ristikko, &other_ristikko])
+            .unwrap();
+
+        // An explicit tab of one gym is a derived tab of another
+        let err = config(
+            r#"[2108, { id = 4567, tab_prefix = "Kivi", tabs = { gym_bouldering = "Ristikko - Boulderit" } }]"#,
+        )
+        .check_sheet_names([&ristikko, &gym(4567, "Kivi Vallila")])
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("both use tab 'Ristikko - Boulderit'"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn gym_config_test() {
+        let gyms: Gyms = toml::from_str(
+            r#"gyms = [2108, { id = 3456, tab_prefix = "Kivi Vallila", tabs = { gym_sportclimbing = "Vallila" } }]"#,
+        )
+        .unwrap();
+        assert_eq!(gyms.gyms[0], GymConfig::from(2108));
+        assert_eq!(gyms.gyms[1].id, 3456);
+        assert_eq!(gyms.gyms[1].tab_prefix.as_deref(), Some("Kivi Vallila"));
+        assert_eq!(gyms.gyms[1].tabs["gym_sportclimbing"], "Vallila");
+    }
+
+    #[test]
+    fn gym_overrides_test() {
+        let config: Config = toml::from_str(
+            r##"
+            vertical_life_email = "test@example.com"
+            vertical_life_password = "password"
+            new_climb_background_color = "#00ff00"
+            gyms = [
+                2108,
+                { id = 3456, new_climb_days = 14, wall_categories = { gym_bouldering = "B" }, columns = [{ field = "difficulty" }] },
+            ]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.gym_columns(2108).len(), default_columns().len());
+        assert_eq!(config.gym_columns(3456).len(), 1);
+        assert_eq!(config.gym_new_climb_days(2108), 7);
+        assert_eq!(config.gym_new_climb_days(3456), 14);
+        assert_eq!(config.gym_new_climb_background_color(3456), "#00ff00");
+        assert_eq!(config.gym_wall_categories(2108).len(), 2);
+        assert_eq!(config.gym_wall_categories(3456)["gym_bouldering"], "B");
+    }
+
+    #[test]
+    fn legacy_column_idxs_test() {
+        let config: Config = toml::from_str(
+            r##"
+            vertical_life_email = "test@example.com"
+            vertical_life_password = "password"
+            new_climb_background_color = "#00ff00"
+            gyms = [2108]
+            climb_color_column_idx = 0
+            grade_column_idx = 2
+            date_column_idx = 3
+            "##,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn sheet_ids_test() {
+        let config: Config = toml::from_str(
+            r##"
+            sheet_id = "main"
+            vertical_life_email = "test@example.com"
+            vertical_life_password = "password"
+            new_climb_background_color = "#00ff00"
+            gyms = [2108, { id = 3456, sheet_id = "vallila" }, { id = 4567, sheet_id = "main" }]
+            "##,
+        )
+        .unwrap();
+        assert_eq!(config.gym_sheet_id(2108), "main");
+        assert_eq!(config.gym_sheet_id(3456), "vallila");
+        assert_eq!(config.sheet_ids(), ["main", "vallila"]);
+    }
+
+    #[test]
+    fn parse_location_from_gym_name_test() {
+        assert_eq!(
+            parse_location_from_gym_name("Kiipeilyareena Ristikko"),
+            Some("Ristikko")
+        );
+        assert_eq!(parse_location_from_gym_name("Ristikko"), None);
+        assert_eq!(
+            parse_location_from_gym_name("Boulderkeskus Kivi Vallila"),
+            None
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+
+use crate::{climb_sheet::GymSheetRow, sheets::Row, vertical_life::Climb};
+
+/// Header of the changelog tab, matching the columns of GymDiff::changelog_row
+pub const CHANGELOG_HEADER: [&str; 6] = [
+    "Aika (UTC)",
+    "Sali",
+    "Lisätty",
+    "Päivitetty",
+    "Poistettu",
+    "Uudet",
+];
+
+/// Columns that archive rows have after the configured ones, see GymSheetRow::to_archive_row
+pub const ARCHIVE_COLUMN_TITLES: [&str; 2] = ["Poistettu", "Päiviä"];
+
+/// Changes a sync makes to a gym's sheets
+#[derive(Debug)]
+pub struct GymDiff {
+    pub gym_id: u32,
+    pub gym_name: String,
+    pub sheets: Vec<SheetDiff>,
+    pub archive: Option<ArchiveDiff>,
+}
+
+/// Changes a sync makes to a single sheet
+#[derive(Debug)]
+pub struct SheetDiff {
+    pub sheet_name: String,
+    pub sheet_id_num: i32,
+    /// Zero indexed column where the sheet's table starts
+    pub first_column_idx: i32,
+    /// Rows whose cells are rewritten to match the climb in Vertical Life. Row indices are those
+    /// of the sheet before any changes, so updates are applied before rows are removed.
+    pub updated_rows: Vec<RowUpdate>,
+    /// Climbs that are not in the sheet yet
+    pub new_climbs: Vec<Climb>,
+    /// Rows of climbs that no longer exist in Vertical Life. Empty when there are no climbs for
+    /// the gym at all, as that most likely means something is off with Vertical Life, not that
+    /// every wall was stripped at once.
+    pub removed_rows: Vec<GymSheetRow>,
+    /// Columns the sheet is sorted by after the changes, empty if it doesn't need sorting
+    pub sort_column_idxs: Vec<i32>,
+    /// Zero indexed positions of the rows below the header that are highlighted as new, in the
+    /// sorted sheet
+    pub new_row_idxs: Vec<usize>,
+}
+
+/// Cells of an existing row that are rewritten
+#[derive(Debug)]
+pub struct RowUpdate {
+    /// Zero indexed row number, header being row 0
+    pub row_idx: i32,
+    pub climb: Climb,
+    pub changes: Vec<CellChange>,
+}
+
+#[derive(Debug)]
+pub struct CellChange {
+    /// Position of the cell in a row as returned by Climb::to_sheet_row
+    pub column: usize,
+    pub old: String,
+    pub new: String,
+}
+
+/// Rows appended to gym's archive tab. The tab is created when missing.
+#[derive(Debug)]
+pub struct ArchiveDiff {
+    pub sheet_name: String,
+    pub rows: Vec<Row>,
+}
+
+impl GymDiff {
+    pub fn new_climbs(&self) -> impl Iterator<Item = &Climb> {
+        self.sheets.iter().flat_map(|s| s.new_climbs.iter())
+    }
+
+    pub fn removed_rows(&self) -> impl Iterator<Item = &GymSheetRow> {
+        self.sheets.iter().flat_map(|s| s.removed_rows.iter())
+    }
+
+    /// Row for the changelog tab: run time, gym, counts of added, updated and removed climbs and
+    /// the labels and grades of the added climbs, for example "A1 6A, B2 7A+"
+    pub fn changelog_row(&self, run_at: DateTime<Utc>) -> Row {
+        let updated_count = self
+            .sheets
+            .iter()
+            .map(|s| s.updated_rows.len())
+            .sum::<usize>();
+        let new_climbs = self
+            .new_climbs()
+            .map(|c| format!("{} {}", c.route_card_label, c.difficulty))
+            .collect::<Vec<_>>();
+        vec![
+            run_at.format("%Y-%m-%d %H:%M").to_string(),
+            self.gym_name.clone(),
+            new_climbs.len().to_string(),
+            updated_count.to_string(),
+            self.removed_rows().count().to_string(),
+            new_climbs.join(", "),
+        ]
+    }
+}
+
+impl SheetDiff {
+    pub fn new(sheet_name: &str, sheet_id_num: i32, first_column_idx: i32) -> Self {
+        Self {
+            sheet_name: sheet_name.to_string(),
+            sheet_id_num,
+            first_column_idx,
+            updated_rows: vec![],
+            new_climbs: vec![],
+            removed_rows: vec![],
+            sort_column_idxs: vec![],
+            new_row_idxs: vec![],
+        }
+    }
+}
+
+impl fmt::Display for GymDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.gym_name)?;
+        for sheet in &self.sheets {
+            write!(f, "{sheet}")?;
+        }
+        if let Some(archive) = &self.archive {
+            writeln!(f, "  {}", archive.sheet_name)?;
+            for row in &archive.rows {
+                writeln!(f, "    + {}", row.join(" | "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SheetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "  {}", self.sheet_name)?;
+        for update in &self.updated_rows {
+            let changes = update
+                .changes
+                .iter()
+                .map(|c| format!("'{}' -> '{}'", c.old, c.new))
+                .collect::<Vec<_>>();
+            writeln!(
+                f,
+                "    ~ row {}: {} ({})",
+                update.row_idx + 1,
+                update.climb,
+                changes.join(", ")
+            )?;
+        }
+        for climb in &self.new_climbs {
+            writeln!(f, "    + {} (color {})", climb, climb.color)?;
+        }
+        for row in &self.removed_rows {
+            writeln!(f, "    - row {}: {}", row.row_idx + 1, row.row)?;
+        }
+        if !self.sort_column_idxs.is_empty() {
+            let columns = self
+                .sort_column_idxs
+                .iter()
+                .map(|idx| idx.to_string())
+                .collect::<Vec<_>>();
+            writeln!(f, "    sort by columns {}", columns.join(", "))?;
+        }
+        writeln!(f, "    highlight {} rows as new", self.new_row_idxs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::config;
+
+    fn test_diff() -> GymDiff {
+        let climb = |id, label: &str, difficulty: &str| Climb {
+            route_card_label: label.to_string(),
+            difficulty: difficulty.to_string(),
+            set_at: Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap(),
+            ..Climb::test(id)
+        };
+        let columns = config::default_columns();
+        let removed = climb(3, "C3", "5+");
+        let mut sheet = SheetDiff::new("Ristikko - Boulderit", 7, 0);
+        sheet.new_climbs = vec![climb(1, "A1", "6A"), climb(2, "B2", "7A+")];
+        sheet.updated_rows = vec![RowUpdate {
+            row_idx: 2,
+            climb: climb(4, "D4", "6B"),
+            changes: vec![CellChange {
+                column: 2,
+                old: "6A+".to_string(),
+                new: "6B".to_string(),
+            }],
+        }];
+        sheet.removed_rows = vec![GymSheetRow::new(
+            &sheet.sheet_name,
+            7,
+            5,
+            removed.to_sheet_row(&columns),
+            vec![],
+            &columns,
+        )];
+        GymDiff {
+            gym_id: 2108,
+            gym_name: "Kiipeilyareena Ristikko".to_string(),
+            sheets: vec![sheet],
+            archive: None,
+        }
+    }
+
+    #[test]
+    fn changelog_row_test() {
+        let run_at = Utc.with_ymd_and_hms(2024, 3, 2, 6, 30, 0).unwrap();
+        assert_eq!(
+            test_diff().changelog_row(run_at),
+            [
+                "2024-03-02 06:30",
+                "Kiipeilyareena Ristikko",
+                "2",
+                "1",
+                "1",
+                "A1 6A, B2 7A+"
+            ]
+        );
+    }
+
+    #[test]
+    fn display_test() {
+        let mut diff = test_diff();
+        diff.sheets[0].sort_column_idxs = vec![3];
+        diff.sheets[0].new_row_idxs = vec![0, 1];
+        diff.archive = Some(ArchiveDiff {
+            sheet_name: "Ristikko - Arkisto".to_string(),
+            rows: vec![vec!["C3".to_string(), "5+".to_string()]],
+        });
+        assert_eq!(
+            diff.to_string(),
+            concat!(
+                "Kiipeilyareena Ristikko\n",
+                "  Ristikko - Boulderit\n",
+                "    ~ row 3: D4 | 6B | 1.3.2024 | Matti | Seinä 1 ('6A+' -> '6B')\n",
+                "    + A1 | 6A | 1.3.2024 | Matti | Seinä 1 (color #ff0000)\n",
+                "    + B2 | 7A+ | 1.3.2024 | Matti | Seinä 1 (color #ff0000)\n",
+                "    - row 6: C3 | 5+ | 1.3.2024 | Matti | Seinä 1\n",
+                "    sort by columns 3\n",
+                "    highlight 2 rows as new\n",
+                "  Ristikko - Arkisto\n",
+                "    + C3 | 5+\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+
+use eyre::Result;
+use lettre::{
+    message::MultiPart, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::ExposeSecret;
+use tracing::*;
+
+use crate::{
+    config::EmailConfig,
+    notify::{GymNotification, NotificationClimb},
+    util::escape_html,
+};
+
+/// Digest of the new climbs as plain text and HTML
+#[derive(Debug)]
+pub struct Digest {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// New climbs of gym grouped by sector, sectors in alphabetical order
+fn climbs_by_sector(gym: &GymNotification) -> BTreeMap<&str, Vec<&NotificationClimb>> {
+    let mut sectors: BTreeMap<&str, Vec<&NotificationClimb>> = BTreeMap::new();
+    for climb in &gym.new_climbs {
+        sectors.entry(&climb.sector).or_default().push(climb);
+    }
+    sectors
+}
+
+/// Build digest of the gyms' new climbs, or None if there are no new climbs
+pub fn digest(notifications: &[GymNotification]) -> Option<Digest> {
+    let gyms = notifications
+        .iter()
+        .filter(|n| !n.new_climbs.is_empty())
+        .collect::<Vec<_>>();
+    if gyms.is_empty() {
+        return None;
+    }
+
+    let count = gyms.iter().map(|g| g.new_climbs.len()).sum::<usize>();
+    let subject = format!("{count} new climb(s)");
+
+    let mut text = String::new();
+    let mut html = String::from("<html><body>");
+    for gym in gyms {
+        text.push_str(&format!("{}\n", gym.gym));
+        html.push_str(&format!("<h2>{}</h2>", escape_html(&gym.gym)));
+        for (sector, climbs) in climbs_by_sector(gym) {
+            text.push_str(&format!("\n  {sector}\n"));
+            html.push_str(&format!("<h3>{}</h3><ul>", escape_html(sector)));
+            for climb in climbs {
+                text.push_str(&format!(
+                    "  - {} {} ({}, {}) {}\n",
+                    climb.label, climb.grade, climb.color, climb.setter, climb.share_url
+                ));
+                html.push_str(&format!(
+                    concat!(
+                        r#"<li><span style="display:inline-block;width:12px;height:12px;"#,
+                        r#"border:1px solid #999;background:{}"></span> "#,
+                        r#"<a href="{}">{} {}</a> {}</li>"#
+                    ),
+                    escape_html(&climb.color),
+                    escape_html(&climb.share_url),
+                    escape_html(&climb.label),
+                    escape_html(&climb.grade),
+                    escape_html(&climb.setter),
+                ));
+            }
+            html.push_str("</ul>");
+        }
+        text.push('\n');
+    }
+    html.push_str("</body></html>");
+
+    Some(Digest {
+        subject,
+        text,
+        html,
+    })
+}
+
+/// Email digest of the new climbs to the configured recipients. Nothing is sent if there are no
+/// new climbs.
+pub async fn send_digest(config: &EmailConfig, notifications: &[GymNotification]) -> Result<()> {
+    let Some(digest) = digest(notifications) else {
+        return Ok(());
+    };
+
+    let mut builder = Message::builder()
+        .from(config.from.parse()?)
+        .subject(digest.subject);
+    for to in &config.to {
+        builder = builder.to(to.parse()?);
+    }
+    let message = builder.multipart(MultiPart::alternative_plain_html(digest.text, digest.html))?;
+
+    let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.smtp_host)?
+        .port(config.smtp_port)
+        .credentials(Credentials::new(
+            config.username.clone(),
+            config.password.expose_secret().clone(),
+        ))
+        .build();
+    info!(recipients = config.to.len(), "sending email digest");
+    transport.send(message).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertical_life::Climb;
+
+    fn climb(label: &str, sector: &str) -> NotificationClimb {
+        NotificationClimb::from(&Climb {
+            route_card_label: label.to_string(),
+            sector_name: sector.to_string(),
+            ..Climb::test(1)
+        })
+    }
+
+    #[test]
+    fn digest_test() {
+        let notifications = [
+            GymNotification {
+                gym: "Kiipeilyareena Ristikko".to_string(),
+                new_climbs: vec![climb("B1", "Sektori 2"), climb("A1", "Sektori 1")],
+                removed_climbs: vec![],
+            },
+            GymNotification {
+                gym: "Kiipeilyareena Kalasatama".to_string(),
+                new_climbs: vec![],
+                removed_climbs: vec![],
+            },
+        ];
+        assert!(digest(&notifications[1..]).is_none());
+        let digest = digest(&notifications).unwrap();
+        assert_eq!(digest.subject, "2 new climb(s)");
+        assert_eq!(
+            digest.text,
+            "Kiipeilyareena Ristikko\n\
+             \n  Sektori 1\n  - A1 6A (#ff0000, Matti) https://example.com/1\n\
+             \n  Sektori 2\n  - B1 6A (#ff0000, Matti) https://example.com/1\n\n"
+        );
+        assert!(digest.html.contains("background:#ff0000"));
+        assert!(!digest.html.contains("Kalasatama"));
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use atom_syndication::{Entry, Feed, Link, Person, Text};
+use chrono::{DateTime, Duration, Utc};
+use eyre::Result;
+use tracing::*;
+
+use crate::{config::FeedConfig, util::slug, vertical_life::Climb};
+
+/// File name of the feed with all gyms' climbs
+const COMBINED_FEED_FILE_NAME: &str = "all.xml";
+
+/// Climbs currently on gym's walls
+#[derive(Debug)]
+pub struct GymClimbs {
+    pub gym_name: String,
+    pub climbs: Vec<Climb>,
+}
+
+fn climb_entry(gym_name: &str, climb: &Climb) -> Entry {
+    let published = climb.set_at.fixed_offset();
+    Entry {
+        id: format!("urn:vertical-life:climb:{}", climb.id),
+        title: Text::plain(format!(
+            "{} {} ({})",
+            climb.route_card_label, climb.difficulty, climb.sector_name
+        )),
+        updated: published,
+        published: Some(published),
+        authors: vec![Person {
+            name: climb.route_setter.clone(),
+            ..Default::default()
+        }],
+        links: vec![Link {
+            href: climb.share_url.clone(),
+            ..Default::default()
+        }],
+        summary: Some(Text::plain(format!(
+            "{gym_name}, {}, {}: {} {}, color {}, set by {}",
+            climb.sector_name,
+            climb.parent_name,
+            climb.route_card_label,
+            climb.difficulty,
+            climb.color,
+            climb.route_setter
+        ))),
+        ..Default::default()
+    }
+}
+
+/// Atom feed of climbs set within the last `days` days before now, newest first
+pub fn build_feed<'a>(
+    title: &str,
+    id: &str,
+    climbs: impl Iterator<Item = (&'a str, &'a Climb)>,
+    days: i64,
+    now: DateTime<Utc>,
+) -> Feed {
+    let since = now - Duration::days(days);
+    let mut climbs = climbs
+        .filter(|(_, c)| c.set_at >= since)
+        .collect::<Vec<_>>();
+    climbs.sort_by(|(_, a), (_, b)| b.set_at.cmp(&a.set_at).then(a.id.cmp(&b.id)));
+    Feed {
+        title: Text::plain(title),
+        id: id.to_string(),
+        updated: climbs.first().map_or(now, |(_, c)| c.set_at).fixed_offset(),
+        entries: climbs
+            .into_iter()
+            .map(|(gym_name, climb)| climb_entry(gym_name, climb))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// File name of gym's feed, for example "kiipeilyareena-ristikko.xml"
+pub fn gym_feed_file_name(gym_name: &str) -> String {
+    format!("{}.xml", slug(gym_name))
+}
+
+/// Feeds to write, as file paths and the feeds: one for each gym and a combined one
+pub fn build_feeds(
+    config: &FeedConfig,
+    gyms: &[GymClimbs],
+    now: DateTime<Utc>,
+) -> Vec<(PathBuf, Feed)> {
+    let mut feeds = gyms
+        .iter()
+        .map(|gym| {
+            let file_name = gym_feed_file_name(&gym.gym_name);
+            let feed = build_feed(
+                &format!("New climbs at {}", gym.gym_name),
+                &format!("urn:climbsheet:{file_name}"),
+                gym.climbs.iter().map(|c| (gym.gym_name.as_str(), c)),
+                config.days,
+                now,
+            );
+            (config.directory.join(file_name), feed)
+        })
+        .collect::<Vec<_>>();
+    let combined = build_feed(
+        "New climbs",
+        &format!("urn:climbsheet:{COMBINED_FEED_FILE_NAME}"),
+        gyms.iter()
+            .flat_map(|g| g.climbs.iter().map(|c| (g.gym_name.as_str(), c))),
+        config.days,
+        now,
+    );
+    feeds.push((config.directory.join(COMBINED_FEED_FILE_NAME), combined));
+    feeds
+}
+
+/// Regenerate the feed files, replacing the ones written by the previous run
+pub fn write_feeds(config: &FeedConfig, gyms: &[GymClimbs]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(&config.directory)?;
+    build_feeds(config, gyms, Utc::now())
+        .into_iter()
+        .map(|(path, feed)| {
+            info!(?path, entries = feed.entries.len(), "writing feed");
+            write_file(&path, &feed.to_string())?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Write to a temporary file first, so that a web server never serves a half written feed
+fn write_file(path: &Path, contents: &str) -> Result<()> {
+    let tmp_path = path.with_extension("xml.tmp");
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn climb(id: u32, set_at: DateTime<Utc>) -> Climb {
+        Climb {
+            set_at,
+            ..Climb::test(id)
+        }
+    }
+
+    #[test]
+    fn build_feeds_test() {
+        let now = Utc.with_ymd_and_hms(2023, 3, 31, 12, 0, 0).unwrap();
+        let gyms = [GymClimbs {
+            gym_name: "Kiipeilyareena Ristikko".to_string(),
+            climbs: vec![
+                climb(1, now - Duration::days(40)),
+                climb(2, now - Duration::days(2)),
+                climb(3, now - Duration::days(1)),
+            ],
+        }];
+        let config = FeedConfig {
+            directory: PathBuf::from("/feeds"),
+            days: 30,
+        };
+        let feeds = build_feeds(&config, &gyms, now);
+
+        let paths = feeds.iter().map(|(p, _)| p.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("/feeds/kiipeilyareena-ristikko.xml"),
+                PathBuf::from("/feeds/all.xml")
+            ]
+        );
+        let (_, feed) = &feeds[0];
+        let links = feed
+            .entries
+            .iter()
+            .map(|e| e.links[0].href.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            links,
+            vec!["https://example.com/3", "https://example.com/2"]
+        );
+        assert_eq!(feed.updated, (now - Duration::days(1)).fixed_offset());
+        assert_eq!(
+            feed.entries[0].published,
+            Some((now - Duration::days(1)).fixed_offset())
+        );
+    }
+}
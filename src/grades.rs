@@ -0,0 +1,415 @@
+//! Parsing climbing grades into keys that sort in order of difficulty. Boulder grades
+//! (Fontainebleau and V-scale) are placed on the Fontainebleau scale and route grades (French,
+//! UIAA and YDS) on the French scale, so grades of the same discipline compare across systems.
+//! Conversions between systems are approximate, as they always are.
+
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use crate::vertical_life::Climb;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Discipline {
+    Boulder,
+    Route,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GradeSystem {
+    /// Fontainebleau, for example "6A+"
+    Font,
+    /// V-scale, for example "V4"
+    VScale,
+    /// French sport, for example "6a+"
+    French,
+    /// UIAA, for example "7-"
+    Uiaa,
+    /// Yosemite Decimal System, for example "5.10a"
+    Yds,
+}
+
+/// Fontainebleau grades in order. Index in the list is the grade's step on the boulder scale.
+const FONT_GRADES: [&str; 24] = [
+    "3", "4", "4+", "5", "5+", "6A", "6A+", "6B", "6B+", "6C", "6C+", "7A", "7A+", "7B", "7B+",
+    "7C", "7C+", "8A", "8A+", "8B", "8B+", "8C", "8C+", "9A",
+];
+
+/// French grades in order. Index in the list is the grade's step on the route scale.
+const FRENCH_GRADES: [&str; 32] = [
+    "1", "2", "3", "4a", "4b", "4c", "5a", "5b", "5c", "6a", "6a+", "6b", "6b+", "6c", "6c+", "7a",
+    "7a+", "7b", "7b+", "7c", "7c+", "8a", "8a+", "8b", "8b+", "8c", "8c+", "9a", "9a+", "9b",
+    "9b+", "9c",
+];
+
+/// Grades of the other systems as the range of steps they cover on their discipline's scale
+const V_GRADES: [(&str, &str, &str); 19] = [
+    ("VB", "3", "3"),
+    ("V0", "4", "4"),
+    ("V1", "5", "5"),
+    ("V2", "5+", "5+"),
+    ("V3", "6A", "6A+"),
+    ("V4", "6B", "6B+"),
+    ("V5", "6C", "6C+"),
+    ("V6", "7A", "7A"),
+    ("V7", "7A+", "7A+"),
+    ("V8", "7B", "7B+"),
+    ("V9", "7C", "7C"),
+    ("V10", "7C+", "7C+"),
+    ("V11", "8A", "8A"),
+    ("V12", "8A+", "8A+"),
+    ("V13", "8B", "8B"),
+    ("V14", "8B+", "8B+"),
+    ("V15", "8C", "8C"),
+    ("V16", "8C+", "8C+"),
+    ("V17", "9A", "9A"),
+];
+
+const UIAA_GRADES: [(&str, &str, &str); 25] = [
+    ("3", "3", "3"),
+    ("4-", "3", "4a"),
+    ("4", "4a", "4a"),
+    ("4+", "4b", "4b"),
+    ("5-", "4c", "4c"),
+    ("5", "5a", "5a"),
+    ("5+", "5b", "5b"),
+    ("6-", "5c", "5c"),
+    ("6", "6a", "6a"),
+    ("6+", "6a+", "6a+"),
+    ("7-", "6b", "6b"),
+    ("7", "6b+", "6b+"),
+    ("7+", "6c", "6c+"),
+    ("8-", "7a", "7a"),
+    ("8", "7a+", "7b"),
+    ("8+", "7b+", "7c"),
+    ("9-", "7c+", "7c+"),
+    ("9", "8a", "8a+"),
+    ("9+", "8b", "8b"),
+    ("10-", "8b+", "8b+"),
+    ("10", "8c", "8c"),
+    ("10+", "8c+", "9a"),
+    ("11-", "9a+", "9a+"),
+    ("11", "9b", "9b"),
+    ("11+", "9b+", "9c"),
+];
+
+const YDS_GRADES: [(&str, &str, &str); 28] = [
+    ("5.5", "4a", "4a"),
+    ("5.6", "4b", "4c"),
+    ("5.7", "5a", "5a"),
+    ("5.8", "5b", "5b"),
+    ("5.9", "5c", "5c"),
+    ("5.10a", "6a", "6a"),
+    ("5.10b", "6a+", "6a+"),
+    ("5.10c", "6b", "6b"),
+    ("5.10d", "6b+", "6b+"),
+    ("5.11a", "6b+", "6b+"),
+    ("5.11b", "6c", "6c"),
+    ("5.11c", "6c+", "6c+"),
+    ("5.11d", "7a", "7a"),
+    ("5.12a", "7a+", "7a+"),
+    ("5.12b", "7b", "7b"),
+    ("5.12c", "7b+", "7b+"),
+    ("5.12d", "7c", "7c"),
+    ("5.13a", "7c+", "7c+"),
+    ("5.13b", "8a", "8a"),
+    ("5.13c", "8a+", "8a+"),
+    ("5.13d", "8b", "8b"),
+    ("5.14a", "8b+", "8b+"),
+    ("5.14b", "8c", "8c"),
+    ("5.14c", "8c+", "8c+"),
+    ("5.14d", "9a", "9a"),
+    ("5.15a", "9a+", "9a+"),
+    ("5.15b", "9b", "9b"),
+    ("5.15c", "9b+", "9c"),
+];
+
+/// Bare French grades that some gyms use below 6a
+const FRENCH_ALIASES: [(&str, &str); 4] = [("4", "4a"), ("4+", "4c"), ("5", "5a"), ("5+", "5c")];
+
+/// A parsed grade. Grades are ordered by discipline first and then by difficulty, and grades of
+/// the same difficulty are equal regardless of their system.
+#[derive(Debug, Clone, Copy)]
+pub struct Grade {
+    pub discipline: Discipline,
+    pub system: GradeSystem,
+    /// Sum of the first and last step the grade covers on its discipline's scale, so that split
+    /// grades like "6a/6a+" fall between the grades they are split between
+    pub key: u32,
+}
+
+impl PartialEq for Grade {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Grade {}
+
+impl Hash for Grade {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.discipline, self.key).hash(state);
+    }
+}
+
+impl Ord for Grade {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.discipline, self.key).cmp(&(other.discipline, other.key))
+    }
+}
+
+impl PartialOrd for Grade {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Discipline {
+    pub fn of_climb(climb: &Climb) -> Self {
+        if climb.item_type == "gym_boulder" {
+            Self::Boulder
+        } else {
+            Self::Route
+        }
+    }
+}
+
+fn scale_step(scale: &[&str], grade: &str) -> Option<u32> {
+    scale.iter().position(|g| *g == grade).map(|idx| idx as u32)
+}
+
+/// First and last step of grade on the scale, for grades listed as ranges like V_GRADES
+fn range_steps(grades: &[(&str, &str, &str)], scale: &[&str], grade: &str) -> Option<(u32, u32)> {
+    let (_, first, last) = grades.iter().find(|(g, _, _)| *g == grade)?;
+    Some((scale_step(scale, first)?, scale_step(scale, last)?))
+}
+
+/// Steps of a single, not split grade in discipline. Lettered grades are Fontainebleau for
+/// boulders and French for routes regardless of case. Bare numbers are Fontainebleau for
+/// boulders, and for routes French up to "5+" and UIAA otherwise.
+fn single_steps(grade: &str, discipline: Discipline) -> Option<(GradeSystem, u32, u32)> {
+    let upper = grade.to_uppercase();
+    let lower = grade.to_lowercase();
+    let single = |system, step| Some((system, step, step));
+
+    if upper.starts_with('V') {
+        let (first, last) = range_steps(&V_GRADES, &FONT_GRADES, &upper)?;
+        return Some((GradeSystem::VScale, first, last));
+    }
+    if lower.starts_with("5.") {
+        // "5.10" without a letter is taken as the middle of the range
+        let lower = match lower.as_str() {
+            "5.10" | "5.11" | "5.12" | "5.13" | "5.14" | "5.15" => format!("{lower}b"),
+            _ => lower,
+        };
+        let (first, last) = range_steps(&YDS_GRADES, &FRENCH_GRADES, &lower)?;
+        return Some((GradeSystem::Yds, first, last));
+    }
+
+    match discipline {
+        Discipline::Boulder => single(GradeSystem::Font, scale_step(&FONT_GRADES, &upper)?),
+        Discipline::Route => {
+            if let Some(step) = scale_step(&FRENCH_GRADES, &lower) {
+                return single(GradeSystem::French, step);
+            }
+            if let Some((_, french)) = FRENCH_ALIASES.iter().find(|(g, _)| *g == lower) {
+                return single(GradeSystem::French, scale_step(&FRENCH_GRADES, french)?);
+            }
+            let (first, last) = range_steps(&UIAA_GRADES, &FRENCH_GRADES, &lower)?;
+            Some((GradeSystem::Uiaa, first, last))
+        }
+    }
+}
+
+/// Second half of a split grade written in full. A second half that is only a suffix takes the
+/// rest from the first half: "6a/+" is short for "6a/6a+", "6a/b" for "6a/6b" and "V4/5" for
+/// "V4/V5".
+fn split_last(first: &str, last: &str) -> String {
+    let base = first.trim_end_matches(['+', '-']);
+    if last == "+" {
+        format!("{base}+")
+    } else if last.len() == 1 && last.chars().all(|c| c.is_ascii_alphabetic()) {
+        format!(
+            "{}{last}",
+            base.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        )
+    } else if last.chars().all(|c| c.is_ascii_digit())
+        && base.ends_with(|c: char| c.is_ascii_digit())
+    {
+        format!(
+            "{}{last}",
+            base.trim_end_matches(|c: char| c.is_ascii_digit())
+        )
+    } else {
+        last.to_string()
+    }
+}
+
+impl Grade {
+    /// Parse a grade like "6A+", "6a/6a+", "V4", "7-" or "5.10a". Discipline decides the system
+    /// of grades that could be either, like "6A". Split grades can also be written like "6a/+",
+    /// "6a/b" or "V4/5". Returns None for unknown grades and split grades that go down.
+    pub fn parse(difficulty: &str, discipline: Discipline) -> Option<Self> {
+        let difficulty = difficulty.trim();
+        let (first, last) = match difficulty.split_once('/') {
+            Some((first, last)) => {
+                let first = first.trim();
+                (first.to_string(), split_last(first, last.trim()))
+            }
+            None => (difficulty.to_string(), difficulty.to_string()),
+        };
+        let (system, first_step, _) = single_steps(&first, discipline)?;
+        let (_, _, last_step) = single_steps(&last, discipline)?;
+        if last_step < first_step {
+            return None;
+        }
+        Some(Self {
+            discipline: system.discipline(),
+            system,
+            key: first_step + last_step,
+        })
+    }
+
+    pub fn of_climb(climb: &Climb) -> Option<Self> {
+        Self::parse(&climb.difficulty, Discipline::of_climb(climb))
+    }
+
+    /// The grade in another system of the same discipline, for example "V4" for "6B+". Grades
+    /// between two grades of the scale are written split, like "6a/6a+", and grades of the other
+    /// systems are rounded to the closest one. Returns None for systems of the other discipline.
+    pub fn convert(&self, system: GradeSystem) -> Option<String> {
+        if system.discipline() != self.discipline {
+            return None;
+        }
+        let (scale, grades): (&[&str], &[(&str, &str, &str)]) = match system {
+            GradeSystem::Font => (&FONT_GRADES, &[]),
+            GradeSystem::French => (&FRENCH_GRADES, &[]),
+            GradeSystem::VScale => (&FONT_GRADES, &V_GRADES),
+            GradeSystem::Uiaa => (&FRENCH_GRADES, &UIAA_GRADES),
+            GradeSystem::Yds => (&FRENCH_GRADES, &YDS_GRADES),
+        };
+        if grades.is_empty() {
+            let step = (self.key / 2) as usize;
+            return Some(if step * 2 == self.key as usize {
+                scale.get(step)?.to_string()
+            } else {
+                format!("{}/{}", scale.get(step)?, scale.get(step + 1)?)
+            });
+        }
+        grades
+            .iter()
+            .filter_map(|(grade, first, last)| {
+                let key = scale_step(scale, first)? + scale_step(scale, last)?;
+                Some((key.abs_diff(self.key), *grade))
+            })
+            // The first of the closest, so the easier one
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, grade)| grade.to_string())
+    }
+}
+
+impl GradeSystem {
+    /// System by its name in config, one of "font", "v", "french", "uiaa" or "yds"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "font" => Some(Self::Font),
+            "v" => Some(Self::VScale),
+            "french" => Some(Self::French),
+            "uiaa" => Some(Self::Uiaa),
+            "yds" => Some(Self::Yds),
+            _ => None,
+        }
+    }
+
+    pub fn discipline(&self) -> Discipline {
+        match self {
+            Self::Font | Self::VScale => Discipline::Boulder,
+            Self::French | Self::Uiaa | Self::Yds => Discipline::Route,
+        }
+    }
+}
+
+impl fmt::Display for GradeSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Font => "Fontainebleau",
+            Self::VScale => "V-scale",
+            Self::French => "French",
+            Self::Uiaa => "UIAA",
+            Self::Yds => "YDS",
+        })
+    }
+}
+
+/// Sort key for difficulty strings of climbs, easiest first and unknown grades last in
+/// alphabetical order
+pub fn difficulty_sort_key(climb: &Climb) -> (bool, Option<Grade>, String) {
+    let grade = Grade::of_climb(climb);
+    (grade.is_none(), grade, climb.difficulty.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(difficulty: &str, discipline: Discipline) -> u32 {
+        Grade::parse(difficulty, discipline).unwrap().key
+    }
+
+    #[test]
+    fn parse_test() {
+        use Discipline::*;
+
+        let boulders = [
+            "4+", "5", "V2", "6A", "6a/6a+", "6A+", "6B", "V4", "7A", "8C+",
+        ];
+        let keys = boulders.map(|d| key(d, Boulder));
+        assert!(keys.windows(2).all(|w| w[0] <= w[1]), "{keys:?}");
+        assert_eq!(key("V4", Boulder), key("6B/6B+", Boulder));
+        assert_eq!(key("V4/5", Boulder), key("V4/V5", Boulder));
+        assert_eq!(Grade::parse("V4", Boulder), Grade::parse("6B/6B+", Boulder));
+
+        let routes = [
+            "4", "5-", "5+", "5.9", "6a", "6a/+", "6+", "6b", "7-", "5.11a", "7a", "9-", "9b+",
+        ];
+        let keys = routes.map(|d| key(d, Route));
+        assert!(keys.windows(2).all(|w| w[0] <= w[1]), "{keys:?}");
+        assert_eq!(key("6", Route), key("6a", Route));
+        assert_eq!(key("5.10a", Route), key("6a", Route));
+        assert_eq!(key("6a/b", Route), key("6a/6b", Route));
+        assert_eq!(key("5.10a/b", Route), key("5.10a/5.10b", Route));
+        assert_eq!(Grade::parse("6b/6a", Route), None);
+
+        assert_eq!(
+            Grade::parse("7-", Route).map(|g| g.system),
+            Some(GradeSystem::Uiaa)
+        );
+        assert_eq!(
+            Grade::parse("6A", Route).map(|g| g.system),
+            Some(GradeSystem::French)
+        );
+        assert_eq!(Grade::parse("Keltainen", Boulder), None);
+        assert!(Grade::parse("8A", Boulder) < Grade::parse("3", Route));
+    }
+
+    #[test]
+    fn convert_test() {
+        use Discipline::*;
+        use GradeSystem::*;
+
+        let convert = |difficulty, discipline, system| {
+            Grade::parse(difficulty, discipline)
+                .and_then(|g| g.convert(system))
+                .unwrap_or_default()
+        };
+        assert_eq!(convert("6B+", Boulder, VScale), "V4");
+        assert_eq!(convert("V4", Boulder, Font), "6B/6B+");
+        assert_eq!(convert("7A", Boulder, VScale), "V6");
+        assert_eq!(convert("6a+", Route, Yds), "5.10b");
+        assert_eq!(convert("7+", Route, French), "6c/6c+");
+        assert_eq!(convert("6b+", Route, Uiaa), "7");
+        assert_eq!(convert("6b+", Route, VScale), "");
+    }
+}
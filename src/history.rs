@@ -0,0 +1,261 @@
+use std::{collections::HashSet, path::Path};
+
+use chrono::{DateTime, Utc};
+use eyre::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use tracing::*;
+
+use crate::vertical_life::Climb;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS climbs (
+    id INTEGER PRIMARY KEY,
+    gym_id INTEGER NOT NULL,
+    gym_name TEXT NOT NULL,
+    item_type TEXT NOT NULL,
+    route_card_label TEXT NOT NULL,
+    difficulty TEXT NOT NULL,
+    color TEXT NOT NULL,
+    set_at TEXT NOT NULL,
+    route_setter TEXT NOT NULL,
+    sector_name TEXT NOT NULL,
+    parent_name TEXT NOT NULL,
+    share_url TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL,
+    removed_at TEXT
+);
+CREATE TABLE IF NOT EXISTS climb_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    climb_id INTEGER NOT NULL REFERENCES climbs (id),
+    changed_at TEXT NOT NULL,
+    attribute TEXT NOT NULL,
+    old_value TEXT NOT NULL,
+    new_value TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS climbs_gym_id ON climbs (gym_id);
+";
+
+/// Local SQLite database of every climb seen, for questions the sheet can't answer since it only
+/// shows the climbs currently on the walls. Each climb has the time it was first and last seen,
+/// the time it was removed and the changes to its attributes in climb_changes.
+pub struct History {
+    connection: Connection,
+}
+
+/// What recording a gym's climbs changed in the history
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct HistoryUpdate {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+}
+
+/// Attributes of climb that are tracked for changes, as column names and values
+fn climb_attributes(climb: &Climb) -> [(&'static str, String); 9] {
+    [
+        ("item_type", climb.item_type.clone()),
+        ("route_card_label", climb.route_card_label.clone()),
+        ("difficulty", climb.difficulty.clone()),
+        ("color", climb.color.clone()),
+        ("set_at", climb.set_at.to_rfc3339()),
+        ("route_setter", climb.route_setter.clone()),
+        ("sector_name", climb.sector_name.clone()),
+        ("parent_name", climb.parent_name.clone()),
+        ("share_url", climb.share_url.clone()),
+    ]
+}
+
+impl History {
+    /// Open the database at path, creating it if it doesn't exist
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self> {
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Record the climbs currently on gym's walls: new climbs are inserted, changed attributes
+    /// are recorded and updated, and climbs that are no longer seen are marked removed. Climbs
+    /// that reappear are no longer removed. Like with the sheets, nothing is marked removed if
+    /// there are no climbs, see diff::SheetDiff::removed_rows.
+    pub fn record_gym(
+        &mut self,
+        gym_id: u32,
+        gym_name: &str,
+        climbs: &[&Climb],
+        seen_at: DateTime<Utc>,
+    ) -> Result<HistoryUpdate> {
+        let seen_at = seen_at.to_rfc3339();
+        let mut update = HistoryUpdate::default();
+        let transaction = self.connection.transaction()?;
+
+        for climb in climbs {
+            let attributes = climb_attributes(climb);
+            let columns = attributes
+                .iter()
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(", ");
+            let existing: Option<Vec<String>> = transaction
+                .query_row(
+                    &format!("SELECT {columns} FROM climbs WHERE id = ?1"),
+                    params![climb.id],
+                    |row| (0..attributes.len()).map(|idx| row.get(idx)).collect(),
+                )
+                .optional()?;
+
+            match existing {
+                None => {
+                    transaction.execute(
+                        "INSERT INTO climbs (id, gym_id, gym_name, item_type, route_card_label,
+                            difficulty, color, set_at, route_setter, sector_name, parent_name,
+                            share_url, first_seen, last_seen)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)",
+                        params![
+                            climb.id,
+                            gym_id,
+                            gym_name,
+                            attributes[0].1,
+                            attributes[1].1,
+                            attributes[2].1,
+                            attributes[3].1,
+                            attributes[4].1,
+                            attributes[5].1,
+                            attributes[6].1,
+                            attributes[7].1,
+                            attributes[8].1,
+                            seen_at,
+                        ],
+                    )?;
+                    update.added += 1;
+                }
+                Some(old_values) => {
+                    let mut changed = false;
+                    for ((attribute, new_value), old_value) in attributes.iter().zip(old_values) {
+                        if *new_value == old_value {
+                            continue;
+                        }
+                        changed = true;
+                        transaction.execute(
+                            "INSERT INTO climb_changes
+                                (climb_id, changed_at, attribute, old_value, new_value)
+                            VALUES (?1, ?2, ?3, ?4, ?5)",
+                            params![climb.id, seen_at, attribute, old_value, new_value],
+                        )?;
+                        transaction.execute(
+                            &format!("UPDATE climbs SET {attribute} = ?1 WHERE id = ?2"),
+                            params![new_value, climb.id],
+                        )?;
+                    }
+                    if changed {
+                        update.changed += 1;
+                    }
+                    transaction.execute(
+                        "UPDATE climbs SET last_seen = ?1, removed_at = NULL WHERE id = ?2",
+                        params![seen_at, climb.id],
+                    )?;
+                }
+            }
+        }
+
+        if climbs.is_empty() {
+            warn!(?gym_id, "no climbs for gym, not marking anything removed");
+        } else {
+            let seen_ids = climbs.iter().map(|c| c.id).collect::<HashSet<_>>();
+            let current_ids = transaction
+                .prepare("SELECT id FROM climbs WHERE gym_id = ?1 AND removed_at IS NULL")?
+                .query_map(params![gym_id], |row| row.get::<_, u32>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            for id in current_ids.into_iter().filter(|id| !seen_ids.contains(id)) {
+                transaction.execute(
+                    "UPDATE climbs SET removed_at = ?1 WHERE id = ?2",
+                    params![seen_at, id],
+                )?;
+                update.removed += 1;
+            }
+        }
+
+        transaction.commit()?;
+        Ok(update)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn climb(id: u32, difficulty: &str) -> Climb {
+        Climb {
+            difficulty: difficulty.to_string(),
+            set_at: Utc.with_ymd_and_hms(2025, 2, 1, 12, 0, 0).unwrap(),
+            ..Climb::test(id)
+        }
+    }
+
+    #[test]
+    fn record_gym_test() {
+        let mut history = History::open_in_memory().unwrap();
+        let day = |d| Utc.with_ymd_and_hms(2025, 3, d, 13, 0, 0).unwrap();
+        let (a, b) = (climb(1, "7A"), climb(2, "6A"));
+
+        let update = history
+            .record_gym(2108, "Kiipeilyareena Ristikko", &[&a, &b], day(1))
+            .unwrap();
+        assert_eq!(
+            update,
+            HistoryUpdate {
+                added: 2,
+                changed: 0,
+                removed: 0
+            }
+        );
+
+        let b = climb(2, "7A");
+        let update = history
+            .record_gym(2108, "Kiipeilyareena Ristikko", &[&b], day(2))
+            .unwrap();
+        assert_eq!(
+            update,
+            HistoryUpdate {
+                added: 0,
+                changed: 1,
+                removed: 1
+            }
+        );
+
+        let (old_value, new_value): (String, String) = history
+            .connection
+            .query_row(
+                "SELECT old_value, new_value FROM climb_changes WHERE climb_id = 2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((old_value.as_str(), new_value.as_str()), ("6A", "7A"));
+        let removed_at: Option<String> = history
+            .connection
+            .query_row("SELECT removed_at FROM climbs WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(removed_at, Some(day(2).to_rfc3339()));
+        let count_set: u32 = history
+            .connection
+            .query_row(
+                "SELECT COUNT(*) FROM climbs
+                WHERE gym_id = 2108 AND difficulty = '7A' AND set_at LIKE '2025%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count_set, 2);
+    }
+}
@@ -0,0 +1,183 @@
+use std::path::Path;
+
+use eyre::Result;
+use tracing::*;
+
+use crate::{
+    climb_sheet::ClimbSheetRow,
+    config::{ColumnField, Config},
+    stats::Tab,
+    util::{escape_html, slug},
+};
+
+/// Filters the table rows by the text typed to the filter input
+const FILTER_SCRIPT: &str = r#"
+const input = document.getElementById("filter");
+input.addEventListener("input", () => {
+  const query = input.value.toLowerCase();
+  for (const row of document.querySelectorAll("tbody tr")) {
+    row.hidden = !row.textContent.toLowerCase().includes(query);
+  }
+});
+"#;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 1em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.5em; text-align: left; }
+nav a { margin-right: 1em; }
+"#;
+
+/// File name of the tab's page, for example "ristikko-boulderit.html"
+pub fn file_name(tab: &Tab) -> String {
+    format!("{}.html", slug(&tab.title))
+}
+
+fn render_nav(pages: &[Tab]) -> String {
+    let links = pages
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<a href="{}">{}</a>"#,
+                escape_html(&file_name(p)),
+                escape_html(&p.title)
+            )
+        })
+        .collect::<String>();
+    format!(r#"<nav><a href="index.html">Etusivu</a>{links}</nav>"#)
+}
+
+fn render_document(title: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            r#"<html><head><meta charset="utf-8"><title>{title}</title>"#,
+            "<style>{style}</style></head><body>{body}</body></html>\n"
+        ),
+        title = escape_html(title),
+        style = STYLE,
+        body = body
+    )
+}
+
+/// Table of page's climbs with the configured columns. Like in the sheets, hold color is the
+/// background of the color columns and the dates of new climbs are highlighted.
+pub fn render_page(config: &Config, page: &Tab, pages: &[Tab]) -> String {
+    let all_columns = config.gym_columns(page.gym_id);
+    let columns = all_columns
+        .iter()
+        .filter(|c| !c.field.is_hidden())
+        .collect::<Vec<_>>();
+    let header = columns
+        .iter()
+        .map(|c| format!("<th>{}</th>", escape_html(c.title())))
+        .collect::<String>();
+    let rows = page
+        .climbs
+        .iter()
+        .map(|climb| {
+            let is_new = ClimbSheetRow::from_climb(climb, all_columns)
+                .is_new(config.gym_new_climb_days(page.gym_id));
+            let cells = columns
+                .iter()
+                .map(|column| {
+                    let background = if column.has_color() {
+                        Some(climb.color.as_str())
+                    } else if column.field == ColumnField::SetAt && is_new {
+                        Some(config.gym_new_climb_background_color(page.gym_id))
+                    } else {
+                        None
+                    };
+                    let style = background
+                        .map(|b| format!(r#" style="background:{}""#, escape_html(b)))
+                        .unwrap_or_default();
+                    let value = match column.field {
+                        ColumnField::ShareUrl => format!(
+                            r#"<a href="{}">{}</a>"#,
+                            escape_html(&climb.share_url),
+                            escape_html(column.format())
+                        ),
+                        _ => escape_html(&column.sheet_value(climb)),
+                    };
+                    format!("<td{style}>{value}</td>")
+                })
+                .collect::<String>();
+            format!("<tr>{cells}</tr>")
+        })
+        .collect::<String>();
+
+    render_document(
+        &page.title,
+        &format!(
+            concat!(
+                "{nav}<h1>{title}</h1>",
+                r#"<input id="filter" type="search" placeholder="Suodata">"#,
+                "<table><thead><tr>{header}</tr></thead><tbody>{rows}</tbody></table>",
+                "<script>{script}</script>"
+            ),
+            nav = render_nav(pages),
+            title = escape_html(&page.title),
+            header = header,
+            rows = rows,
+            script = FILTER_SCRIPT
+        ),
+    )
+}
+
+pub fn render_index(pages: &[Tab]) -> String {
+    let items = pages
+        .iter()
+        .map(|p| {
+            format!(
+                r#"<li><a href="{}">{}</a> ({})</li>"#,
+                escape_html(&file_name(p)),
+                escape_html(&p.title),
+                p.climbs.len()
+            )
+        })
+        .collect::<String>();
+    render_document(
+        "Climbsheet",
+        &format!("<h1>Climbsheet</h1><ul>{items}</ul>"),
+    )
+}
+
+/// Write index.html and a page for each of gym's tabs to directory
+pub fn write_site(config: &Config, directory: &Path, pages: &[Tab]) -> Result<()> {
+    std::fs::create_dir_all(directory)?;
+    std::fs::write(directory.join("index.html"), render_index(pages))?;
+    for page in pages {
+        let path = directory.join(file_name(page));
+        info!(?path, climbs = page.climbs.len(), "writing page");
+        std::fs::write(path, render_page(config, page, pages))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertical_life;
+
+    #[test]
+    fn render_page_test() {
+        let config = Config::test();
+        let climb = vertical_life::Climb {
+            parent_name: "Seinä <1>".to_string(),
+            ..vertical_life::Climb::test(1)
+        };
+        let pages = [Tab {
+            title: "Ristikko - Boulderit".to_string(),
+            gym_id: 2108,
+            climbs: vec![climb],
+        }];
+        assert_eq!(file_name(&pages[0]), "ristikko-boulderit.html");
+
+        let html = render_page(&config, &pages[0], &pages);
+        assert!(html.contains(r##"<td style="background:#ff0000"></td><td>A1</td>"##));
+        assert!(html.contains(r##"<td style="background:#00ff00">"##));
+        assert!(html.contains("<td>Seinä &lt;1&gt;</td>"));
+        assert!(html.contains(r#"<a href="https://example.com/1">🔗</a>"#));
+        assert!(!html.contains("<th>Id</th>"));
+    }
+}
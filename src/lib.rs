@@ -1,5 +1,16 @@
 pub mod climb_sheet;
 pub mod config;
+pub mod diff;
+pub mod email;
+pub mod feed;
+pub mod grades;
+pub mod history;
+pub mod html;
+pub mod notify;
+pub mod setters;
 pub mod setup;
 pub mod sheets;
+pub mod sink;
+pub mod stats;
+pub mod util;
 pub mod vertical_life;
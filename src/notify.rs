@@ -0,0 +1,236 @@
+use eyre::Result;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::*;
+
+use crate::{
+    config::{Webhook, WebhookFormat},
+    diff::GymDiff,
+    vertical_life::Climb,
+};
+
+/// New and removed climbs of a gym, as sent to webhooks
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GymNotification {
+    pub gym: String,
+    pub new_climbs: Vec<NotificationClimb>,
+    pub removed_climbs: Vec<RemovedClimb>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NotificationClimb {
+    pub id: u32,
+    pub label: String,
+    pub grade: String,
+    pub color: String,
+    pub sector: String,
+    pub wall: String,
+    pub setter: String,
+    pub share_url: String,
+    pub boulder: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RemovedClimb {
+    /// Missing for rows added before ids were written to sheet
+    pub id: Option<u32>,
+    /// Row of the climb in the sheet, for example "A1 | 6A | 1.2.2023 | Matti | Seinä 1"
+    pub row: String,
+}
+
+impl From<&Climb> for NotificationClimb {
+    fn from(climb: &Climb) -> Self {
+        Self {
+            id: climb.id,
+            label: climb.route_card_label.clone(),
+            grade: climb.difficulty.clone(),
+            color: climb.color.clone(),
+            sector: climb.sector_name.clone(),
+            wall: climb.parent_name.clone(),
+            setter: climb.route_setter.clone(),
+            share_url: climb.share_url.clone(),
+            boulder: climb.item_type == "gym_boulder",
+        }
+    }
+}
+
+impl From<&GymDiff> for GymNotification {
+    fn from(diff: &GymDiff) -> Self {
+        Self {
+            gym: diff.gym_name.clone(),
+            new_climbs: diff.new_climbs().map(NotificationClimb::from).collect(),
+            removed_climbs: diff
+                .removed_rows()
+                .map(|r| RemovedClimb {
+                    id: r.climb_id,
+                    row: r.row.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl GymNotification {
+    pub fn is_empty(&self) -> bool {
+        self.new_climbs.is_empty() && self.removed_climbs.is_empty()
+    }
+
+    /// Human readable summary for chat messages, for example:
+    ///
+    /// ```text
+    /// 2 new boulders at Kiipeilyareena Ristikko
+    /// • A1 6A, red, Sector 1, Matti https://...
+    /// ```
+    pub fn message(&self) -> String {
+        let mut lines = vec![];
+        let (boulders, routes): (Vec<_>, Vec<_>) = self.new_climbs.iter().partition(|c| c.boulder);
+        for (climbs, singular, plural) in [
+            (boulders, "boulder", "boulders"),
+            (routes, "route", "routes"),
+        ] {
+            if climbs.is_empty() {
+                continue;
+            }
+            let noun = if climbs.len() == 1 { singular } else { plural };
+            lines.push(format!("{} new {noun} at {}", climbs.len(), self.gym));
+            lines.extend(climbs.iter().map(|c| {
+                format!(
+                    "• {} {}, {}, {}, {} {}",
+                    c.label, c.grade, c.color, c.sector, c.setter, c.share_url
+                )
+            }));
+        }
+        if !self.removed_climbs.is_empty() {
+            let noun = if self.removed_climbs.len() == 1 {
+                "climb"
+            } else {
+                "climbs"
+            };
+            lines.push(format!(
+                "{} {noun} removed from {}",
+                self.removed_climbs.len(),
+                self.gym
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Body of the webhook request in the format the target expects
+fn payload(format: WebhookFormat, notifications: &[GymNotification]) -> serde_json::Value {
+    let message = || {
+        notifications
+            .iter()
+            .map(|n| n.message())
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    };
+    match format {
+        WebhookFormat::Json => json!({ "gyms": notifications }),
+        WebhookFormat::Slack => json!({ "text": message() }),
+        WebhookFormat::Discord => json!({ "content": message() }),
+    }
+}
+
+pub async fn send_webhook(webhook: &Webhook, notifications: &[GymNotification]) -> Result<()> {
+    reqwest::Client::new()
+        .post(webhook.url.expose_secret())
+        .json(&payload(webhook.format, notifications))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Send gyms with new or removed climbs to all webhooks. Nothing is sent if nothing changed.
+/// Failing webhooks are logged so that one broken target doesn't stop the others.
+pub async fn send_webhooks(webhooks: &[Webhook], notifications: &[GymNotification]) {
+    let notifications = notifications
+        .iter()
+        .filter(|n| !n.is_empty())
+        .cloned()
+        .collect::<Vec<_>>();
+    if notifications.is_empty() {
+        return;
+    }
+
+    for webhook in webhooks {
+        info!(?webhook.format, "sending webhook");
+        if let Err(err) = send_webhook(webhook, &notifications).await {
+            error!(?err, ?webhook.format, "failed to send webhook");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    fn notification() -> GymNotification {
+        GymNotification {
+            gym: "Kiipeilyareena Ristikko".to_string(),
+            new_climbs: vec![NotificationClimb::from(&Climb::test(1))],
+            removed_climbs: vec![],
+        }
+    }
+
+    /// Accept a single request and return its body
+    async fn receive_request(listener: TcpListener) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        let body_start = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(idx) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break idx + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let content_length = headers
+            .lines()
+            .find_map(|l| l.strip_prefix("content-length: "))
+            .and_then(|l| l.trim().parse::<usize>().ok())
+            .unwrap();
+        while request.len() < body_start + content_length {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request[body_start..].to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn send_webhook_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = tokio::spawn(receive_request(listener));
+
+        let webhook = Webhook {
+            url: Secret::new(url),
+            format: WebhookFormat::Json,
+        };
+        send_webhook(&webhook, &[notification()]).await.unwrap();
+
+        let body: serde_json::Value = serde_json::from_str(&server.await.unwrap()).unwrap();
+        let gyms: Vec<GymNotification> = serde_json::from_value(body["gyms"].clone()).unwrap();
+        assert_eq!(gyms, vec![notification()]);
+    }
+
+    #[test]
+    fn message_test() {
+        assert_eq!(
+            notification().message(),
+            "1 new boulder at Kiipeilyareena Ristikko\n• A1 6A, #ff0000, Sektori 1, Matti https://example.com/1"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use eyre::Result;
+
+use crate::{grades::difficulty_sort_key, sheets::Row, vertical_life::Climb};
+
+/// Columns of the setter report before the climbs set per month
+const KEY_COLUMN_TITLES: [&str; 6] = [
+    "Sali",
+    "Rakentaja",
+    "Reittejä",
+    "Helpoin",
+    "Vaikein",
+    "Tyypillinen",
+];
+
+/// Climbs currently on the walls of a gym by one setter
+#[derive(Debug, PartialEq, Eq)]
+pub struct SetterStats {
+    pub gym_name: String,
+    pub setter: String,
+    pub climb_count: usize,
+    /// Climbs set per month, keyed by month like "2025-03"
+    pub per_month: BTreeMap<String, usize>,
+    pub easiest_grade: String,
+    pub hardest_grade: String,
+    /// Most common grade, the easier one on a tie
+    pub typical_grade: String,
+}
+
+/// Stats of each setter in each gym, ordered by gym and then by climb count. Grades are compared
+/// by difficulty, see grades::Grade, and unknown grades count as the hardest.
+pub fn setter_stats<'a>(
+    gyms: impl IntoIterator<Item = (&'a str, &'a [Climb])>,
+) -> Vec<SetterStats> {
+    let mut stats = vec![];
+    for (gym_name, climbs) in gyms {
+        let mut by_setter: BTreeMap<&str, Vec<&Climb>> = BTreeMap::new();
+        for climb in climbs {
+            by_setter
+                .entry(climb.route_setter.as_str())
+                .or_default()
+                .push(climb);
+        }
+
+        let mut gym_stats = by_setter
+            .into_iter()
+            .map(|(setter, climbs)| {
+                let mut per_month = BTreeMap::new();
+                let mut per_grade: BTreeMap<_, usize> = BTreeMap::new();
+                for climb in &climbs {
+                    *per_month
+                        .entry(climb.set_at.format("%Y-%m").to_string())
+                        .or_default() += 1;
+                    *per_grade.entry(difficulty_sort_key(climb)).or_default() += 1;
+                }
+                let grade_or_empty = |grade: Option<&(_, _, String)>| {
+                    grade.map(|(_, _, g)| g.clone()).unwrap_or_default()
+                };
+                SetterStats {
+                    gym_name: gym_name.to_string(),
+                    setter: setter.to_string(),
+                    climb_count: climbs.len(),
+                    per_month,
+                    easiest_grade: grade_or_empty(per_grade.keys().next()),
+                    hardest_grade: grade_or_empty(per_grade.keys().next_back()),
+                    typical_grade: grade_or_empty(
+                        per_grade
+                            .iter()
+                            .rev()
+                            .max_by_key(|(_, count)| **count)
+                            .map(|(grade, _)| grade),
+                    ),
+                }
+            })
+            .collect::<Vec<_>>();
+        gym_stats.sort_by_key(|s| std::cmp::Reverse(s.climb_count));
+        stats.extend(gym_stats);
+    }
+    stats
+}
+
+/// Header and rows of the setter report, with a column for each month that climbs were set in
+pub fn report_rows(stats: &[SetterStats]) -> (Row, Vec<Row>) {
+    let months = stats
+        .iter()
+        .flat_map(|s| s.per_month.keys())
+        .collect::<BTreeSet<_>>();
+    let header = KEY_COLUMN_TITLES
+        .into_iter()
+        .map(String::from)
+        .chain(months.iter().map(|m| m.to_string()))
+        .collect();
+    let rows = stats
+        .iter()
+        .map(|s| {
+            [
+                s.gym_name.clone(),
+                s.setter.clone(),
+                s.climb_count.to_string(),
+                s.easiest_grade.clone(),
+                s.hardest_grade.clone(),
+                s.typical_grade.clone(),
+            ]
+            .into_iter()
+            .chain(
+                months
+                    .iter()
+                    .map(|m| s.per_month.get(*m).copied().unwrap_or(0).to_string()),
+            )
+            .collect()
+        })
+        .collect();
+    (header, rows)
+}
+
+/// Rows as a plain text table with the columns padded to the same width
+pub fn format_table(header: &Row, rows: &[Row]) -> String {
+    let widths = header
+        .iter()
+        .enumerate()
+        .map(|(idx, title)| {
+            rows.iter()
+                .map(|r| r[idx].chars().count())
+                .chain([title.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    std::iter::once(header)
+        .chain(rows)
+        .map(|row| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(value, width)| format!("{value:width$}"))
+                .collect::<Vec<_>>()
+                .join("  ");
+            line.trim_end().to_string() + "\n"
+        })
+        .collect()
+}
+
+/// Rows as CSV with the header first
+pub fn format_csv(header: &Row, rows: &[Row]) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    writer.write_record(header)?;
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn climb(difficulty: &str, route_setter: &str, month: u32) -> Climb {
+        Climb {
+            difficulty: difficulty.to_string(),
+            set_at: chrono::Utc
+                .with_ymd_and_hms(2025, month, 1, 12, 0, 0)
+                .unwrap(),
+            route_setter: route_setter.to_string(),
+            ..Climb::test(1)
+        }
+    }
+
+    #[test]
+    fn report_rows_test() {
+        let climbs = vec![
+            climb("6A", "Matti", 2),
+            climb("7A", "Matti", 3),
+            climb("6B", "Matti", 3),
+            climb("6B", "Matti", 3),
+            climb("5", "Liisa", 3),
+        ];
+        let stats = setter_stats([("Kiipeilyareena Ristikko", climbs.as_slice())]);
+        let (header, rows) = report_rows(&stats);
+
+        assert_eq!(
+            header,
+            [
+                "Sali",
+                "Rakentaja",
+                "Reittejä",
+                "Helpoin",
+                "Vaikein",
+                "Tyypillinen",
+                "2025-02",
+                "2025-03"
+            ]
+        );
+        let rows = rows.iter().map(|r| r.join("|")).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "Kiipeilyareena Ristikko|Matti|4|6A|7A|6B|1|3",
+                "Kiipeilyareena Ristikko|Liisa|1|5|5|5|0|1",
+            ]
+        );
+    }
+}
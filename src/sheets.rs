@@ -2,14 +2,17 @@ extern crate google_sheets4 as sheets4;
 
 use std::{collections::HashMap, path::Path, sync::Arc};
 
+use chrono::NaiveDate;
 use eyre::Result;
 use lazy_static::lazy_static;
-
-use regex::Regex;
 use sheets4::{
     api::{
-        AppendValuesResponse, BatchUpdateSpreadsheetRequest, CellData, CellFormat, Color,
-        GridRange, RepeatCellRequest, Request, SortRangeRequest, SortSpec, ValueRange,
+        AddSheetRequest, AppendCellsRequest, AppendDimensionRequest, BatchUpdateSpreadsheetRequest,
+        CellData, CellFormat, ClearValuesRequest, Color, DeleteDimensionRequest,
+        DimensionProperties, DimensionRange, DuplicateSheetRequest, ExtendedValue, GridCoordinate,
+        GridProperties, GridRange, NumberFormat, RepeatCellRequest, Request, Response, RowData,
+        SheetProperties, SortRangeRequest, SortSpec, TextFormat, UpdateCellsRequest,
+        UpdateDimensionPropertiesRequest, UpdateValuesResponse, ValueRange,
     },
     hyper::{self, client::HttpConnector},
     hyper_rustls::HttpsConnector,
@@ -49,20 +52,165 @@ pub async fn append_row(
     sheet_id: &str,
     sheet_name: &str,
     row: Row,
-) -> Result<sheets4::api::AppendValuesResponse> {
+) -> Result<UpdateValuesResponse> {
+    append_rows(sheets, sheet_id, sheet_name, vec![row]).await
+}
+
+/// Write rows below the last row with data, starting from the header's first column. The range is
+/// computed here instead of using values append, because values append lines the rows up wrong
+/// when the table has only a header that does not start from the first column. Unlike values
+/// append, values update doesn't add rows, so the grid is grown to fit first.
+pub async fn append_rows(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    sheet_name: &str,
+    rows: Vec<Row>,
+) -> Result<UpdateValuesResponse> {
+    let existing_rows = get_sheet_rows(sheets, sheet_id, sheet_name).await?;
+    let first_column_idx = first_column_idx(&existing_rows);
+    let properties = get_sheet_properties(sheets, sheet_id, sheet_name).await?;
+    batch_update(
+        sheets,
+        sheet_id,
+        grow_grid_requests(
+            &properties,
+            existing_rows.len() + rows.len(),
+            first_column_idx + rows.iter().map(Vec::len).max().unwrap_or(0),
+        ),
+    )
+    .await?;
+
+    let range = next_rows_range(sheet_name, &existing_rows);
     let request = ValueRange {
         major_dimension: None,
         range: None,
-        values: Some(vec![row]),
+        values: Some(rows),
     };
-    let range = sheet_name;
     let request = sheets
         .spreadsheets()
-        .values_append(request, sheet_id, range)
-        .insert_data_option("INSERT_ROWS")
+        .values_update(request, sheet_id, &range)
         .value_input_option("USER_ENTERED");
-    let (_, append_values_res) = request.doit().await?;
-    Ok(append_values_res)
+    let (_, update_values_res) = request.doit().await?;
+    Ok(update_values_res)
+}
+
+/// Replace all values of sheet with rows, starting from the first cell. The grid is grown to fit
+/// the rows first.
+pub async fn replace_rows(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    sheet_name: &str,
+    rows: Vec<Row>,
+) -> Result<UpdateValuesResponse> {
+    let properties = get_sheet_properties(sheets, sheet_id, sheet_name).await?;
+    batch_update(
+        sheets,
+        sheet_id,
+        grow_grid_requests(
+            &properties,
+            rows.len(),
+            rows.iter().map(Vec::len).max().unwrap_or(0),
+        ),
+    )
+    .await?;
+
+    let range = format!("'{}'", sheet_name.replace('\'', "''"));
+    sheets
+        .spreadsheets()
+        .values_clear(ClearValuesRequest::default(), sheet_id, &range)
+        .doit()
+        .await?;
+    let request = ValueRange {
+        major_dimension: None,
+        range: None,
+        values: Some(rows),
+    };
+    let (_, update_values_res) = sheets
+        .spreadsheets()
+        .values_update(request, sheet_id, &format!("{range}!A1"))
+        .value_input_option("USER_ENTERED")
+        .doit()
+        .await?;
+    Ok(update_values_res)
+}
+
+/// Properties of sheet, including its numeric id and grid size
+async fn get_sheet_properties(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    sheet_name: &str,
+) -> Result<SheetProperties> {
+    let (_, res) = sheets
+        .spreadsheets()
+        .get(sheet_id)
+        .add_ranges(&format!("'{}'", sheet_name.replace('\'', "''")))
+        .param("fields", "sheets.properties")
+        .doit()
+        .await?;
+    res.sheets
+        .into_iter()
+        .flatten()
+        .find_map(|s| s.properties)
+        .ok_or_else(|| eyre::eyre!("expected properties of sheet {sheet_name}"))
+}
+
+/// Add rows and columns to the end of sheet so that its grid has at least row_count rows and
+/// column_count columns
+pub fn grow_grid_requests(
+    properties: &SheetProperties,
+    row_count: usize,
+    column_count: usize,
+) -> Vec<Request> {
+    let grid = properties.grid_properties.clone().unwrap_or_default();
+    [
+        ("ROWS", grid.row_count, row_count),
+        ("COLUMNS", grid.column_count, column_count),
+    ]
+    .into_iter()
+    .filter_map(|(dimension, current, needed)| {
+        let missing = needed as i32 - current.unwrap_or(0);
+        (missing > 0).then(|| Request {
+            append_dimension: Some(AppendDimensionRequest {
+                sheet_id: properties.sheet_id,
+                dimension: Some(dimension.to_string()),
+                length: Some(missing),
+            }),
+            ..Default::default()
+        })
+    })
+    .collect()
+}
+
+/// Zero indexed column of the header's first non-empty cell
+fn first_column_idx(existing_rows: &[Row]) -> usize {
+    existing_rows
+        .first()
+        .and_then(|header| header.iter().position(|v| !v.is_empty()))
+        .unwrap_or(0)
+}
+
+/// Returns A1 notation for the cell below the last row of existing_rows in the header's first
+/// column, for example "'Ristikko - Arkisto'!B13"
+fn next_rows_range(sheet_name: &str, existing_rows: &[Row]) -> String {
+    // Values start from the first row of sheet, so the next row's 1-indexed number is the count of
+    // existing rows + 1
+    format!(
+        "'{}'!{}{}",
+        sheet_name.replace('\'', "''"),
+        column_letter(first_column_idx(existing_rows)),
+        existing_rows.len() + 1
+    )
+}
+
+/// Returns column's letter in A1 notation, for example "B" for 1 and "AA" for 26
+fn column_letter(column_idx: usize) -> String {
+    let mut letters = vec![];
+    let mut n = column_idx + 1;
+    while n > 0 {
+        letters.push((b'A' + ((n - 1) % 26) as u8) as char);
+        n = (n - 1) / 26;
+    }
+    letters.iter().rev().collect()
 }
 
 // Get numeric sheet id for sheet name
@@ -101,6 +249,153 @@ pub async fn sort_sheet_by_column(
     sheet_id_num: i32,
     column_idx: i32,
 ) -> Result<()> {
+    batch_update(
+        sheets,
+        sheet_id,
+        vec![sort_by_column_request(sheet_id_num, column_idx)],
+    )
+    .await
+}
+
+pub async fn set_range_background_color(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    background_color: Option<Color>,
+    grid_range: GridRange,
+) -> Result<()> {
+    batch_update(
+        sheets,
+        sheet_id,
+        vec![background_color_request(background_color, grid_range)],
+    )
+    .await
+}
+
+/// Send requests to spreadsheet in a single batch update. Does nothing if there are no requests.
+pub async fn batch_update(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    requests: Vec<Request>,
+) -> Result<()> {
+    batch_update_with_replies(sheets, sheet_id, requests).await?;
+    Ok(())
+}
+
+/// Like batch_update, but returns a reply for each request
+async fn batch_update_with_replies(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    requests: Vec<Request>,
+) -> Result<Vec<Response>> {
+    if requests.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let req = BatchUpdateSpreadsheetRequest {
+        requests: Some(requests),
+        ..Default::default()
+    };
+
+    let (_, response) = sheets
+        .spreadsheets()
+        .batch_update(req, sheet_id)
+        .doit()
+        .await?;
+
+    Ok(response.replies.unwrap_or_default())
+}
+
+/// Add an empty sheet with the header row frozen. Returns the new sheet's numeric id.
+pub async fn add_sheet(sheets: &SheetsClient, sheet_id: &str, title: &str) -> Result<i32> {
+    let request = Request {
+        add_sheet: Some(AddSheetRequest {
+            properties: Some(SheetProperties {
+                title: Some(title.to_string()),
+                grid_properties: Some(GridProperties {
+                    frozen_row_count: Some(1),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+        }),
+        ..Default::default()
+    };
+    let replies = batch_update_with_replies(sheets, sheet_id, vec![request]).await?;
+    replies
+        .into_iter()
+        .next()
+        .and_then(|r| r.add_sheet)
+        .and_then(|r| r.properties)
+        .and_then(|p| p.sheet_id)
+        .ok_or_else(|| eyre::eyre!("expected sheet id in add sheet response"))
+}
+
+/// Copy a sheet under a new name. Returns the new sheet's numeric id.
+pub async fn duplicate_sheet(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    source_sheet_id_num: i32,
+    new_sheet_name: &str,
+) -> Result<i32> {
+    let request = Request {
+        duplicate_sheet: Some(DuplicateSheetRequest {
+            source_sheet_id: Some(source_sheet_id_num),
+            new_sheet_name: Some(new_sheet_name.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    let replies = batch_update_with_replies(sheets, sheet_id, vec![request]).await?;
+    replies
+        .into_iter()
+        .next()
+        .and_then(|r| r.duplicate_sheet)
+        .and_then(|r| r.properties)
+        .and_then(|p| p.sheet_id)
+        .ok_or_else(|| eyre::eyre!("expected sheet id in duplicate sheet response"))
+}
+
+/// Write a bold header to the first row of sheet, starting from first_column_idx
+pub fn header_request(sheet_id_num: i32, first_column_idx: i32, header: &[&str]) -> Request {
+    let cells = header
+        .iter()
+        .map(|title| CellData {
+            user_entered_format: Some(CellFormat {
+                text_format: Some(TextFormat {
+                    bold: Some(true),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..string_cell(title)
+        })
+        .collect();
+
+    Request {
+        update_cells: Some(UpdateCellsRequest {
+            start: Some(GridCoordinate {
+                sheet_id: Some(sheet_id_num),
+                row_index: Some(0),
+                column_index: Some(first_column_idx),
+            }),
+            rows: Some(vec![RowData {
+                values: Some(cells),
+            }]),
+            fields: Some("userEnteredValue,userEnteredFormat(textFormat)".to_string()),
+            range: None,
+        }),
+        ..Default::default()
+    }
+}
+
+/// Sort sheet's rows below header in descending order by column
+pub fn sort_by_column_request(sheet_id_num: i32, column_idx: i32) -> Request {
+    sort_by_columns_request(sheet_id_num, &[column_idx])
+}
+
+/// Sort sheet's rows below header in descending order by the first column, rows with the same
+/// value by the second column and so on
+pub fn sort_by_columns_request(sheet_id_num: i32, column_idxs: &[i32]) -> Request {
     let sort_range_request = SortRangeRequest {
         range: Some(GridRange {
             sheet_id: Some(sheet_id_num),
@@ -109,33 +404,47 @@ pub async fn sort_sheet_by_column(
             start_row_index: Some(1),
             end_row_index: None,
         }),
-        sort_specs: Some(vec![SortSpec {
-            dimension_index: Some(column_idx),
-            sort_order: Some("DESCENDING".to_string()),
-            ..Default::default()
-        }]),
+        sort_specs: Some(
+            column_idxs
+                .iter()
+                .map(|column_idx| SortSpec {
+                    dimension_index: Some(*column_idx),
+                    sort_order: Some("DESCENDING".to_string()),
+                    ..Default::default()
+                })
+                .collect(),
+        ),
     };
 
-    let sort_request = BatchUpdateSpreadsheetRequest {
-        requests: Some(vec![Request {
-            sort_range: Some(sort_range_request),
-            ..Default::default()
-        }]),
+    Request {
+        sort_range: Some(sort_range_request),
         ..Default::default()
-    };
+    }
+}
 
-    let request = sheets.spreadsheets().batch_update(sort_request, sheet_id);
-    request.doit().await?;
+pub fn delete_rows_requests(sheet_id_num: i32, mut row_indices: Vec<i32>) -> Vec<Request> {
+    // Requests in a batch are applied in order, so delete starting from the bottom to keep the
+    // remaining indices valid
+    row_indices.sort_unstable_by(|a, b| b.cmp(a));
+    row_indices.dedup();
 
-    Ok(())
+    row_indices
+        .into_iter()
+        .map(|row_idx| Request {
+            delete_dimension: Some(DeleteDimensionRequest {
+                range: Some(DimensionRange {
+                    sheet_id: Some(sheet_id_num),
+                    dimension: Some("ROWS".to_string()),
+                    start_index: Some(row_idx),
+                    end_index: Some(row_idx + 1),
+                }),
+            }),
+            ..Default::default()
+        })
+        .collect()
 }
 
-pub async fn set_range_background_color(
-    sheets: &SheetsClient,
-    sheet_id: &str,
-    background_color: Option<Color>,
-    grid_range: GridRange,
-) -> Result<()> {
+pub fn background_color_request(background_color: Option<Color>, grid_range: GridRange) -> Request {
     let style = CellFormat {
         background_color,
         ..Default::default()
@@ -150,21 +459,154 @@ pub async fn set_range_background_color(
         fields: Some("userEnteredFormat(backgroundColor)".to_string()),
     };
 
-    let req = BatchUpdateSpreadsheetRequest {
-        requests: Some(vec![Request {
-            repeat_cell: Some(repeat_cell_req),
-            ..Default::default()
-        }]),
+    Request {
+        repeat_cell: Some(repeat_cell_req),
+        ..Default::default()
+    }
+}
+
+/// Append rows of cells after the last row with data in sheet. Cells' values and formats, apart
+/// from the background color and number format, are as if they were typed in by the user.
+pub fn append_cells_request(sheet_id_num: i32, rows: Vec<Vec<CellData>>) -> Request {
+    Request {
+        append_cells: Some(AppendCellsRequest {
+            sheet_id: Some(sheet_id_num),
+            rows: Some(
+                rows.into_iter()
+                    .map(|values| RowData {
+                        values: Some(values),
+                    })
+                    .collect(),
+            ),
+            fields: Some(
+                "userEnteredValue,userEnteredFormat(backgroundColor,numberFormat)".to_string(),
+            ),
+        }),
         ..Default::default()
+    }
+}
+
+/// Overwrite a single cell's value and number format, and background color if
+/// update_background_color is set
+pub fn update_cell_request(
+    sheet_id_num: i32,
+    row_idx: i32,
+    column_idx: i32,
+    cell: CellData,
+    update_background_color: bool,
+) -> Request {
+    let fields = if update_background_color {
+        "userEnteredValue,userEnteredFormat(backgroundColor,numberFormat)"
+    } else {
+        "userEnteredValue,userEnteredFormat(numberFormat)"
     };
+    Request {
+        update_cells: Some(UpdateCellsRequest {
+            start: Some(GridCoordinate {
+                sheet_id: Some(sheet_id_num),
+                row_index: Some(row_idx),
+                column_index: Some(column_idx),
+            }),
+            rows: Some(vec![RowData {
+                values: Some(vec![cell]),
+            }]),
+            fields: Some(fields.to_string()),
+            range: None,
+        }),
+        ..Default::default()
+    }
+}
 
-    let _response = sheets
-        .spreadsheets()
-        .batch_update(req, sheet_id)
-        .doit()
-        .await?;
+pub fn hide_column_request(sheet_id_num: i32, column_idx: i32) -> Request {
+    Request {
+        update_dimension_properties: Some(UpdateDimensionPropertiesRequest {
+            range: Some(DimensionRange {
+                sheet_id: Some(sheet_id_num),
+                dimension: Some("COLUMNS".to_string()),
+                start_index: Some(column_idx),
+                end_index: Some(column_idx + 1),
+            }),
+            properties: Some(DimensionProperties {
+                hidden_by_user: Some(true),
+                ..Default::default()
+            }),
+            fields: Some("hiddenByUser".to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
 
-    Ok(())
+pub fn string_cell(value: &str) -> CellData {
+    CellData {
+        user_entered_value: Some(ExtendedValue {
+            string_value: Some(value.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn number_cell(value: f64) -> CellData {
+    CellData {
+        user_entered_value: Some(ExtendedValue {
+            number_value: Some(value),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+pub fn formula_cell(formula: &str) -> CellData {
+    CellData {
+        user_entered_value: Some(ExtendedValue {
+            formula_value: Some(formula.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Cell with date as a serial number, so that sheet sorts it like a date, displayed using
+/// pattern, for example "d.m.yyyy"
+pub fn date_cell(date: NaiveDate, pattern: &str) -> CellData {
+    CellData {
+        user_entered_value: Some(ExtendedValue {
+            number_value: Some(date_to_serial_number(date) as f64),
+            ..Default::default()
+        }),
+        user_entered_format: Some(CellFormat {
+            number_format: Some(NumberFormat {
+                type_: Some("DATE".to_string()),
+                pattern: Some(pattern.to_string()),
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+/// Convert chrono format string, for example "%-d.%-m.%Y", to a sheets date pattern
+/// "d.m.yyyy", so that dates written as numbers look the same as when written as text
+pub fn date_pattern_from_chrono_format(format: &str) -> String {
+    [
+        ("%-d", "d"),
+        ("%d", "dd"),
+        ("%-m", "m"),
+        ("%m", "mm"),
+        ("%Y", "yyyy"),
+        ("%y", "yy"),
+    ]
+    .iter()
+    .fold(format.to_string(), |pattern, (from, to)| {
+        pattern.replace(from, to)
+    })
+}
+
+/// Sheets represents dates as days since 30.12.1899
+fn date_to_serial_number(date: NaiveDate) -> i64 {
+    let epoch = NaiveDate::from_ymd_opt(1899, 12, 30).unwrap();
+    date.signed_duration_since(epoch).num_days()
 }
 
 pub async fn get_sheet_rows(
@@ -180,27 +622,45 @@ pub async fn get_sheet_rows(
     Ok(res.values.unwrap_or_default())
 }
 
-/// Returns zero indexed row number
-pub fn get_updated_row_from_update_values_response(
-    append_values_res: &AppendValuesResponse,
-) -> i32 {
-    append_values_res
-        .updates
-        .as_ref()
-        .and_then(|u| u.updated_range.as_ref())
-        .map(|r| parse_row_from_range(r) - 1)
-        .expect("expected row in update values response")
+/// Like get_sheet_rows, but returns cells instead of plain values so that hyperlinks and background
+/// colors are included
+pub async fn get_sheet_cells(
+    sheets: &SheetsClient,
+    sheet_id: &str,
+    sheet_name: &str,
+) -> Result<Vec<Vec<CellData>>> {
+    let request = sheets
+        .spreadsheets()
+        .get(sheet_id)
+        .add_ranges(sheet_name)
+        .include_grid_data(true)
+        .param(
+            "fields",
+            "sheets.data.rowData.values(formattedValue,hyperlink,userEnteredFormat.backgroundColor)",
+        );
+    let (_, res) = request.doit().await?;
+    Ok(res
+        .sheets
+        .into_iter()
+        .flatten()
+        .flat_map(|s| s.data.into_iter().flatten())
+        .flat_map(|d| d.row_data.into_iter().flatten())
+        .map(|r| r.values.unwrap_or_default())
+        .collect())
 }
 
-// Parse row number from range,
-// for example "Ristikko - Reitit'!B12:F12"
-fn parse_row_from_range(range: &str) -> i32 {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r".+![A-Z]+(\d+):[A-Z]+\d+").unwrap();
-    }
+pub fn cell_formatted_value(cell: &CellData) -> String {
+    cell.formatted_value.clone().unwrap_or_default()
+}
 
-    let caps = RE.captures(range).unwrap();
-    caps.get(1).unwrap().as_str().parse::<i32>().unwrap()
+/// Returns a value that recreates the cell when written with USER_ENTERED value input option. For
+/// example, a cell with hyperlink becomes a HYPERLINK formula.
+pub fn cell_user_entered_value(cell: &CellData) -> String {
+    let value = cell_formatted_value(cell);
+    match &cell.hyperlink {
+        Some(url) => format!(r#"=HYPERLINK("{}"; "{}")"#, url, value),
+        None => value,
+    }
 }
 
 pub fn color_from_hex(hex: &str) -> Color {
@@ -215,15 +675,94 @@ pub fn color_from_hex(hex: &str) -> Color {
     }
 }
 
+/// Returns color in format of "#rrggbb"
+pub fn color_to_hex(color: &Color) -> String {
+    let component = |c: Option<f32>| (c.unwrap_or(0.0) * 255.0).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        component(color.red),
+        component(color.green),
+        component(color.blue)
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn row_from_range_test() {
-        let input = "'Ristikko - Reitit'!B12:F12";
-        let expected = 12;
-        let actual = parse_row_from_range(input);
-        assert_eq!(expected, actual);
+    fn next_rows_range_test() {
+        let rows = |rows: &[&[&str]]| {
+            rows.iter()
+                .map(|r| r.iter().map(|v| v.to_string()).collect())
+                .collect::<Vec<Row>>()
+        };
+        assert_eq!(next_rows_range("Arkisto", &[]), "'Arkisto'!A1");
+        assert_eq!(
+            next_rows_range("Ristikko - Arkisto", &rows(&[&["", "Nimi", "Vaikeus"]])),
+            "'Ristikko - Arkisto'!B2"
+        );
+        assert_eq!(
+            next_rows_range(
+                "Ristikko - Arkisto",
+                &rows(&[&["", "Nimi"], &["", "A1"], &["", "A2"]])
+            ),
+            "'Ristikko - Arkisto'!B4"
+        );
+    }
+
+    #[test]
+    fn grow_grid_requests_test() {
+        let properties = SheetProperties {
+            sheet_id: Some(7),
+            grid_properties: Some(GridProperties {
+                row_count: Some(1000),
+                column_count: Some(26),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(grow_grid_requests(&properties, 1000, 26).is_empty());
+
+        let requests = grow_grid_requests(&properties, 1002, 30);
+        let appends = requests
+            .iter()
+            .map(|r| {
+                let append = r.append_dimension.as_ref().unwrap();
+                (append.sheet_id, append.dimension.as_deref(), append.length)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            appends,
+            [
+                (Some(7), Some("ROWS"), Some(2)),
+                (Some(7), Some("COLUMNS"), Some(4))
+            ]
+        );
+    }
+
+    #[test]
+    fn column_letter_test() {
+        assert_eq!(column_letter(0), "A");
+        assert_eq!(column_letter(25), "Z");
+        assert_eq!(column_letter(26), "AA");
+        assert_eq!(column_letter(27), "AB");
+    }
+
+    #[test]
+    fn color_hex_roundtrip_test() {
+        assert_eq!(color_to_hex(&color_from_hex("#1a2B3c")), "#1a2b3c");
+    }
+
+    #[test]
+    fn date_to_serial_number_test() {
+        let date = NaiveDate::from_ymd_opt(2023, 2, 15).unwrap();
+        assert_eq!(date_to_serial_number(date), 44972);
+    }
+
+    #[test]
+    fn date_pattern_from_chrono_format_test() {
+        assert_eq!(date_pattern_from_chrono_format("%-d.%-m.%Y"), "d.m.yyyy");
+        assert_eq!(date_pattern_from_chrono_format("%Y-%m-%d"), "yyyy-mm-dd");
     }
 }
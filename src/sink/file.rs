@@ -0,0 +1,556 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{Datelike, NaiveDate};
+use eyre::Result;
+use serde_json::{Map, Value};
+use tracing::*;
+
+use super::Sink;
+use crate::{
+    climb_sheet::{GymSheet, GymSheetRow},
+    config::{self, Column, ColumnField},
+    diff::{self, GymDiff},
+    sheets::Row,
+    util::slug,
+    vertical_life,
+};
+
+/// Files have no cell backgrounds, so the hold color is kept in an extra column after the
+/// configured ones
+const COLOR_COLUMN_TITLE: &str = "Väri";
+/// Extra column after the color that has "x" for the new climbs that sheets would highlight
+const NEW_COLUMN_TITLE: &str = "Uusi";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Csv,
+    Json,
+}
+
+/// Keeps each sheet in a file of its own in a directory, for example 'Ristikko - Boulderit' in
+/// ristikko-boulderit.csv. Links are written as plain urls.
+pub struct FileSink<'a> {
+    config: &'a config::Config,
+    directory: PathBuf,
+    format: FileFormat,
+}
+
+impl<'a> FileSink<'a> {
+    /// The directory is created on the first write, so that dry runs don't touch the file system
+    pub fn new(config: &'a config::Config, directory: &Path, format: FileFormat) -> Self {
+        Self {
+            config,
+            directory: directory.to_path_buf(),
+            format,
+        }
+    }
+
+    fn path(&self, sheet_name: &str) -> PathBuf {
+        let extension = match self.format {
+            FileFormat::Csv => "csv",
+            FileFormat::Json => "json",
+        };
+        self.directory
+            .join(format!("{}.{extension}", slug(sheet_name)))
+    }
+
+    /// Header of gym's sheets: the configured columns followed by the color and new columns
+    fn gym_header(columns: &[Column]) -> Vec<String> {
+        columns
+            .iter()
+            .map(|c| c.title())
+            .chain([COLOR_COLUMN_TITLE, NEW_COLUMN_TITLE])
+            .map(String::from)
+            .collect()
+    }
+
+    /// Rows of sheet, not including the header, with the values lined up with header. Missing
+    /// sheets have no rows.
+    fn read_rows(&self, sheet_name: &str, header: &[String]) -> Result<Vec<Row>> {
+        let path = self.path(sheet_name);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+
+        let rows = match self.format {
+            FileFormat::Csv => csv::ReaderBuilder::new()
+                .flexible(true)
+                .from_path(&path)?
+                .records()
+                .map(|record| Ok(record?.iter().map(String::from).collect::<Row>()))
+                .collect::<Result<Vec<_>>>()?,
+            FileFormat::Json => {
+                let objects: Vec<Map<String, Value>> =
+                    serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+                objects
+                    .iter()
+                    .map(|object| {
+                        header
+                            .iter()
+                            .map(|title| match object.get(title) {
+                                Some(Value::String(value)) => value.clone(),
+                                Some(Value::Null) | None => String::new(),
+                                Some(value) => value.to_string(),
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .chain(std::iter::repeat(String::new()))
+                    .take(header.len())
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn write_rows(&self, sheet_name: &str, header: &[String], rows: &[Row]) -> Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        let path = self.path(sheet_name);
+        match self.format {
+            FileFormat::Csv => {
+                let mut writer = csv::Writer::from_path(&path)?;
+                writer.write_record(header)?;
+                for row in rows {
+                    writer.write_record(row)?;
+                }
+                writer.flush()?;
+            }
+            FileFormat::Json => {
+                let objects = rows
+                    .iter()
+                    .map(|row| {
+                        header
+                            .iter()
+                            .cloned()
+                            .zip(row.iter().cloned().map(Value::String))
+                            .collect::<Map<_, _>>()
+                    })
+                    .collect::<Vec<_>>();
+                std::fs::write(&path, serde_json::to_string_pretty(&objects)? + "\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn climb_to_row(columns: &[Column], climb: &vertical_life::Climb) -> Row {
+        columns
+            .iter()
+            .map(|c| to_file_value(c, &c.sheet_value(climb)))
+            .chain([climb.color.clone(), String::new()])
+            .collect()
+    }
+}
+
+/// Value as written to file: links are plain urls instead of HYPERLINK formulas
+fn to_file_value(column: &Column, sheet_value: &str) -> String {
+    match column.field {
+        ColumnField::ShareUrl => sheet_value
+            .strip_prefix(r#"=HYPERLINK(""#)
+            .and_then(|rest| rest.split('"').next())
+            .unwrap_or(sheet_value)
+            .to_string(),
+        _ => sheet_value.to_string(),
+    }
+}
+
+/// Value of a column that rows can be sorted by as a number, for example days for dates
+fn sort_value(column: &Column, file_value: &str) -> Option<i64> {
+    match column.field {
+        ColumnField::SetAt => NaiveDate::parse_from_str(file_value, column.format())
+            .ok()
+            .map(|date| date.num_days_from_ce() as i64),
+        _ => file_value.parse().ok(),
+    }
+}
+
+/// Value as it would be in the spreadsheet, so that rows can be compared to climbs the same way
+fn to_sheet_value(column: &Column, file_value: &str) -> String {
+    match column.field {
+        ColumnField::ShareUrl if !file_value.is_empty() => column.hyperlink_formula(file_value),
+        _ => file_value.to_string(),
+    }
+}
+
+#[async_trait]
+impl Sink for FileSink<'_> {
+    fn config(&self) -> &config::Config {
+        self.config
+    }
+
+    /// Files of all gyms are in the same directory, gym's own spreadsheet is not used here
+    fn has_sheet(&self, _gym_id: u32, sheet_name: &str) -> bool {
+        self.path(sheet_name).exists()
+    }
+
+    async fn get_gym_routes_from_sheet(&self, gym: &vertical_life::Gym) -> Result<Vec<GymSheet>> {
+        info!(?gym.id, "getting gym routes from files");
+        let columns = self.config.gym_columns(gym.id);
+        let header = Self::gym_header(columns);
+        self.config
+            .gym_sheet_names(gym)?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, sheet_name)| self.has_sheet(gym.id, sheet_name))
+            .map(|(sheet_id_num, sheet_name)| {
+                let rows = self
+                    .read_rows(&sheet_name, &header)?
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, row)| {
+                        let color = &row[columns.len()];
+                        GymSheetRow::new(
+                            &sheet_name,
+                            sheet_id_num as i32,
+                            // +1 for the header row, like in sheets
+                            idx as i32 + 1,
+                            columns
+                                .iter()
+                                .zip(&row)
+                                .map(|(c, v)| to_sheet_value(c, v))
+                                .collect(),
+                            columns
+                                .iter()
+                                .map(|c| {
+                                    if c.has_color() {
+                                        color.clone()
+                                    } else {
+                                        String::new()
+                                    }
+                                })
+                                .collect(),
+                            columns,
+                        )
+                    })
+                    .collect();
+                Ok(GymSheet {
+                    sheet_name,
+                    sheet_id_num: sheet_id_num as i32,
+                    first_column_idx: 0,
+                    rows,
+                })
+            })
+            .collect()
+    }
+
+    async fn apply_gym_diff(&self, diff: &GymDiff) -> Result<()> {
+        let columns = self.config.gym_columns(diff.gym_id);
+        if let Some(archive) = &diff.archive {
+            info!(
+                archive.sheet_name,
+                count = archive.rows.len(),
+                "archiving rows"
+            );
+            let header = columns
+                .iter()
+                .map(|c| c.title())
+                .chain(diff::ARCHIVE_COLUMN_TITLES)
+                .map(String::from)
+                .collect::<Vec<_>>();
+            let mut rows = self.read_rows(&archive.sheet_name, &header)?;
+            rows.extend(archive.rows.iter().map(|row| {
+                row.iter()
+                    .enumerate()
+                    .map(|(idx, value)| match columns.get(idx) {
+                        Some(column) => to_file_value(column, value),
+                        None => value.clone(),
+                    })
+                    .collect()
+            }));
+            self.write_rows(&archive.sheet_name, &header, &rows)?;
+        }
+
+        let header = Self::gym_header(columns);
+        let color_idx = columns.len();
+        let new_idx = columns.len() + 1;
+        for sheet_diff in &diff.sheets {
+            let mut rows = self.read_rows(&sheet_diff.sheet_name, &header)?;
+
+            for update in &sheet_diff.updated_rows {
+                // -1 for the header row
+                let row = &mut rows[update.row_idx as usize - 1];
+                for change in &update.changes {
+                    let column = &columns[change.column];
+                    row[change.column] = to_file_value(column, &column.sheet_value(&update.climb));
+                    if column.has_color() {
+                        row[color_idx] = update.climb.color.clone();
+                    }
+                }
+            }
+
+            let mut removed_row_idxs = sheet_diff
+                .removed_rows
+                .iter()
+                .map(|r| r.row_idx as usize - 1)
+                .collect::<Vec<_>>();
+            removed_row_idxs.sort_unstable_by(|a, b| b.cmp(a));
+            for idx in removed_row_idxs {
+                rows.remove(idx);
+            }
+
+            rows.extend(
+                sheet_diff
+                    .new_climbs
+                    .iter()
+                    .map(|c| Self::climb_to_row(columns, c)),
+            );
+
+            if !sheet_diff.sort_column_idxs.is_empty() {
+                // Descending like in sheets, rows with an empty or unknown value last
+                rows.sort_by_key(|row| {
+                    sheet_diff
+                        .sort_column_idxs
+                        .iter()
+                        .map(|idx| {
+                            let idx = *idx as usize;
+                            let value = sort_value(&columns[idx], &row[idx]);
+                            (value.is_none(), std::cmp::Reverse(value))
+                        })
+                        .collect::<Vec<_>>()
+                });
+            }
+
+            for (idx, row) in rows.iter_mut().enumerate() {
+                row[new_idx] = if sheet_diff.new_row_idxs.contains(&idx) {
+                    "x".to_string()
+                } else {
+                    String::new()
+                };
+            }
+
+            info!(
+                sheet_diff.sheet_name,
+                row_count = rows.len(),
+                "writing file"
+            );
+            self.write_rows(&sheet_diff.sheet_name, &header, &rows)?;
+        }
+
+        Ok(())
+    }
+
+    async fn create_sheets(
+        &mut self,
+        gym: &vertical_life::Gym,
+        sheet_names: &[String],
+    ) -> Result<()> {
+        let header = Self::gym_header(self.config.gym_columns(gym.id));
+        for sheet_name in sheet_names {
+            info!(sheet_name, "creating file");
+            self.write_rows(sheet_name, &header, &[])?;
+        }
+        Ok(())
+    }
+
+    async fn append_changelog_row(&mut self, row: Row) -> Result<()> {
+        let sheet_name = &self.config.changelog_sheet_name;
+        let header = diff::CHANGELOG_HEADER.map(String::from);
+        let mut rows = self.read_rows(sheet_name, &header)?;
+        rows.push(row);
+        self.write_rows(sheet_name, &header, &rows)
+    }
+
+    async fn write_report(&mut self, sheet_name: &str, header: Row, rows: Vec<Row>) -> Result<()> {
+        self.write_rows(sheet_name, &header, &rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn climb(id: u32, difficulty: &str) -> vertical_life::Climb {
+        vertical_life::Climb {
+            difficulty: difficulty.to_string(),
+            ..vertical_life::Climb::test(id)
+        }
+    }
+
+    fn gym_and_sectors(
+        climbs: Vec<vertical_life::Climb>,
+    ) -> (vertical_life::Gym, Vec<vertical_life::GymSectorFull>) {
+        let gym = vertical_life::Gym {
+            id: 2108,
+            name: "Kiipeilyareena Ristikko".to_string(),
+            boulder_count: climbs.len() as u32,
+            route_count: 0,
+            gym_sectors: vec![],
+        };
+        let sector = vertical_life::GymSectorFull {
+            id: 1,
+            gym_id: 2108,
+            name: "Sektori 1".to_string(),
+            category: "gym_bouldering".to_string(),
+            cover: None,
+            overview: String::new(),
+            route_count: climbs.len() as u32,
+            walls: vec![vertical_life::Wall {
+                id: 1,
+                gym_sector_id: 1,
+                height: 4,
+                name: "Seinä 1".to_string(),
+                category: "gym_bouldering".to_string(),
+                gym_boulders: Some(climbs),
+                gym_routes: None,
+            }],
+        };
+        (gym, vec![sector])
+    }
+
+    async fn sync(sink: &mut FileSink<'_>, climbs: Vec<vertical_life::Climb>) -> GymDiff {
+        let (gym, sectors) = gym_and_sectors(climbs);
+        let missing_sheets = sink.missing_gym_sheets(&gym, &sectors).unwrap();
+        sink.create_sheets(&gym, &missing_sheets).await.unwrap();
+        let diff = sink.diff_gym(&gym, &sectors).await.unwrap();
+        sink.apply_gym_diff(&diff).await.unwrap();
+        diff
+    }
+
+    #[tokio::test]
+    async fn sync_test() {
+        let config = config::Config::test();
+        for format in [FileFormat::Csv, FileFormat::Json] {
+            let directory = std::env::temp_dir().join(format!(
+                "climbsheet-file-sink-test-{}-{format:?}",
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&directory);
+            let mut sink = FileSink::new(&config, &directory, format);
+            // Dry runs only read, so nothing is created before the first write
+            assert!(!directory.exists());
+
+            let diff = sync(&mut sink, vec![climb(1, "6A"), climb(2, "6B")]).await;
+            assert_eq!(diff.new_climbs().count(), 2);
+            assert!(sink.has_sheet(2108, "Ristikko - Boulderit"));
+
+            // Nothing changes when the rows match the climbs
+            let diff = sync(&mut sink, vec![climb(1, "6A"), climb(2, "6B")]).await;
+            assert_eq!(diff.new_climbs().count(), 0);
+            assert!(diff.sheets.iter().all(|s| s.updated_rows.is_empty()));
+
+            let diff = sync(&mut sink, vec![climb(1, "6A+")]).await;
+            assert_eq!(diff.sheets[0].updated_rows.len(), 1);
+            assert_eq!(diff.removed_rows().count(), 1);
+
+            let (gym, _) = gym_and_sectors(vec![]);
+            let sheets = sink.get_gym_routes_from_sheet(&gym).await.unwrap();
+            let rows = &sheets[0].rows;
+            assert_eq!(rows.len(), 1);
+            assert_eq!(rows[0].climb_id, Some(1));
+            assert_eq!(rows[0].values[2], "6A+");
+            assert_eq!(rows[0].colors[0], "#ff0000");
+
+            std::fs::remove_dir_all(&directory).unwrap();
+        }
+    }
+
+    /// Diff of the climbs against a sheet of rows added before ids were written to the sheet
+    async fn legacy_diff(
+        test_name: &str,
+        legacy_climbs: Vec<vertical_life::Climb>,
+        climbs: Vec<vertical_life::Climb>,
+    ) -> GymDiff {
+        let config = config::Config::test();
+        let directory = std::env::temp_dir().join(format!(
+            "climbsheet-file-sink-{test_name}-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        let sink = FileSink::new(&config, &directory, FileFormat::Csv);
+
+        let columns = config.gym_columns(2108);
+        let id_idx = config::column_position(columns, ColumnField::Id).unwrap();
+        let rows = legacy_climbs
+            .iter()
+            .map(|climb| {
+                let mut row = FileSink::climb_to_row(columns, climb);
+                row[id_idx] = String::new();
+                row
+            })
+            .collect::<Vec<_>>();
+        sink.write_rows(
+            "Ristikko - Boulderit",
+            &FileSink::gym_header(columns),
+            &rows,
+        )
+        .unwrap();
+
+        let (gym, sectors) = gym_and_sectors(climbs);
+        let diff = sink.diff_gym(&gym, &sectors).await.unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        diff
+    }
+
+    #[tokio::test]
+    async fn legacy_row_gets_id_test() {
+        let diff = legacy_diff("legacy-id", vec![climb(1, "6A")], vec![climb(1, "6A")]).await;
+        let id_idx =
+            config::column_position(config::Config::test().gym_columns(2108), ColumnField::Id)
+                .unwrap();
+        let sheet_diff = &diff.sheets[0];
+        assert!(sheet_diff.new_climbs.is_empty());
+        assert!(sheet_diff.removed_rows.is_empty());
+        assert_eq!(sheet_diff.updated_rows.len(), 1);
+        let changes = sheet_diff.updated_rows[0]
+            .changes
+            .iter()
+            .map(|c| (c.column, c.old.as_str(), c.new.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [(id_idx, "", "1")]);
+    }
+
+    #[tokio::test]
+    async fn identical_legacy_rows_test() {
+        let diff = legacy_diff(
+            "legacy-identical",
+            vec![climb(1, "6A"), climb(1, "6A")],
+            vec![climb(1, "6A")],
+        )
+        .await;
+        let sheet_diff = &diff.sheets[0];
+        assert!(sheet_diff.new_climbs.is_empty());
+        // One of the rows is kept and gets the id, the other one is removed
+        assert_eq!(sheet_diff.updated_rows.len(), 1);
+        assert_eq!(sheet_diff.removed_rows.len(), 1);
+        assert_ne!(
+            sheet_diff.removed_rows[0].row_idx,
+            sheet_diff.updated_rows[0].row_idx
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_archive_test() {
+        let mut config = config::Config::test();
+        config.archive_sheet_suffix = Some("Arkisto".to_string());
+        let directory = std::env::temp_dir().join(format!(
+            "climbsheet-file-sink-archive-test-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        let mut sink = FileSink::new(&config, &directory, FileFormat::Csv);
+
+        sync(&mut sink, vec![climb(1, "6A"), climb(2, "6B")]).await;
+        assert!(!sink.has_sheet(2108, "Ristikko - Arkisto"));
+        let diff = sync(&mut sink, vec![climb(1, "6A")]).await;
+        assert_eq!(diff.archive.unwrap().sheet_name, "Ristikko - Arkisto");
+
+        let columns = config.gym_columns(2108);
+        let header = columns
+            .iter()
+            .map(|c| c.title())
+            .chain(diff::ARCHIVE_COLUMN_TITLES)
+            .map(String::from)
+            .collect::<Vec<_>>();
+        let rows = sink.read_rows("Ristikko - Arkisto", &header).unwrap();
+        assert_eq!(rows.len(), 1);
+        let id_idx = config::column_position(columns, ColumnField::Id).unwrap();
+        assert_eq!(rows[0][id_idx], "2");
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use eyre::Result;
+use tracing::*;
+
+use crate::{
+    climb_sheet::{ClimbSheetRow, GymSheet, GymSheetRow},
+    config::{self, column_position, ColumnField, SortBy},
+    diff::{ArchiveDiff, CellChange, GymDiff, RowUpdate, SheetDiff},
+    grades::Grade,
+    sheets::Row,
+    vertical_life,
+};
+
+mod file;
+
+pub use file::{FileFormat, FileSink};
+
+/// Columns that gym's sheets are sorted by, in descending order
+fn sort_fields(sort_by: SortBy) -> &'static [ColumnField] {
+    match sort_by {
+        SortBy::Date => &[ColumnField::SetAt],
+        SortBy::Grade => &[ColumnField::GradeOrder, ColumnField::SetAt],
+    }
+}
+
+/// The values of a row that decide its position in a sorted sheet, and whether it's new
+#[derive(Debug, Clone)]
+struct RowOrder {
+    grade_key: Option<u32>,
+    set_at: Option<NaiveDate>,
+    is_new: bool,
+}
+
+impl RowOrder {
+    fn of_climb(
+        climb: &vertical_life::Climb,
+        columns: &[config::Column],
+        new_climb_days: i64,
+    ) -> Self {
+        let row = ClimbSheetRow::from_climb(climb, columns);
+        Self {
+            grade_key: Grade::of_climb(climb).map(|g| g.key),
+            set_at: row.set_at_date(),
+            is_new: row.is_new(new_climb_days),
+        }
+    }
+
+    fn of_row(row: &GymSheetRow, columns: &[config::Column], new_climb_days: i64) -> Self {
+        Self {
+            grade_key: column_position(columns, ColumnField::GradeOrder)
+                .and_then(|idx| row.values.get(idx))
+                .and_then(|v| v.parse().ok()),
+            set_at: row.row.set_at_date(),
+            is_new: row.row.is_new(new_climb_days),
+        }
+    }
+}
+
+/// Positions of the new rows once rows are sorted like sort_fields sorts the sheet. Rows that
+/// would be sorted by an empty cell go last, like in sheets.
+fn new_row_idxs(mut rows: Vec<RowOrder>, sort_by: SortBy) -> Vec<usize> {
+    rows.sort_by_key(|row| {
+        let grade_key = match sort_by {
+            SortBy::Date => None,
+            SortBy::Grade => row.grade_key,
+        };
+        (
+            sort_by == SortBy::Grade && grade_key.is_none(),
+            Reverse(grade_key),
+            row.set_at.is_none(),
+            Reverse(row.set_at),
+        )
+    });
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| row.is_new)
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+/// Output that gym's climbs are synced to: the Google spreadsheet (climb_sheet::ClimbSheet) or
+/// local CSV or JSON files (FileSink). Implementations load the existing rows of gym's sheets and
+/// write the changes in GymDiff back, including marking the new climbs. Figuring out the changes
+/// is shared.
+#[async_trait]
+pub trait Sink: Send + Sync {
+    fn config(&self) -> &config::Config;
+
+    /// Whether gym's spreadsheet has a sheet with the name, for example 'Ristikko - Boulderit'
+    fn has_sheet(&self, gym_id: u32, sheet_name: &str) -> bool;
+
+    /// For a gym, return rows from all the sheets that belong to the gym. For example, for
+    /// Ristikko, you would return rows from Ristikko - Reitit and Ristikko - Boulderit
+    async fn get_gym_routes_from_sheet(&self, gym: &vertical_life::Gym) -> Result<Vec<GymSheet>>;
+
+    /// Write the changes in gym diff
+    async fn apply_gym_diff(&self, diff: &GymDiff) -> Result<()>;
+
+    /// Create empty sheets for gym's climbs
+    async fn create_sheets(
+        &mut self,
+        gym: &vertical_life::Gym,
+        sheet_names: &[String],
+    ) -> Result<()>;
+
+    /// Append a row to the changelog, creating it with a header first if it's missing
+    async fn append_changelog_row(&mut self, row: Row) -> Result<()>;
+
+    /// Replace the rows of a report tab like the statistics, creating it first if it's missing
+    async fn write_report(&mut self, sheet_name: &str, header: Row, rows: Vec<Row>) -> Result<()>;
+
+    /// Names of the sheets that gym's walls belong to but are missing
+    fn missing_gym_sheets(
+        &self,
+        gym: &vertical_life::Gym,
+        sectors: &[vertical_life::GymSectorFull],
+    ) -> Result<Vec<String>> {
+        let mut sheet_names = sectors
+            .iter()
+            .flat_map(|s| s.walls.iter())
+            .filter_map(|w| self.config().gym_sheet_name(gym, &w.category).transpose())
+            .filter(|sheet_name| {
+                sheet_name
+                    .as_ref()
+                    .map_or(true, |name| !self.has_sheet(gym.id, name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        sheet_names.sort();
+        sheet_names.dedup();
+        Ok(sheet_names)
+    }
+
+    /// Compare gym's sheets to the climbs currently in Vertical Life and figure out what needs to
+    /// change: climbs missing from the sheets are appended and rows of climbs that have been
+    /// stripped from the walls are removed
+    async fn diff_gym(
+        &self,
+        gym: &vertical_life::Gym,
+        sectors: &[vertical_life::GymSectorFull],
+    ) -> Result<GymDiff> {
+        let columns = self.config().gym_columns(gym.id);
+        let new_climb_days = self.config().gym_new_climb_days(gym.id);
+        let gym_sheets = self.get_gym_routes_from_sheet(gym).await?;
+        let mut sheet_diffs = gym_sheets
+            .iter()
+            .map(|s| SheetDiff::new(&s.sheet_name, s.sheet_id_num, s.first_column_idx))
+            .collect::<Vec<_>>();
+
+        // Existing rows are matched to climbs by climb id. Rows added before ids were written to
+        // the sheet are matched by their contents instead, and get the id filled in.
+        let mut rows_by_id: HashMap<u32, (usize, usize)> = HashMap::new();
+        let mut rows_without_id: HashMap<&ClimbSheetRow, (usize, usize)> = HashMap::new();
+        for (sheet_idx, gym_sheet) in gym_sheets.iter().enumerate() {
+            for (row_idx, row) in gym_sheet.rows.iter().enumerate() {
+                match row.climb_id {
+                    Some(climb_id) => rows_by_id.insert(climb_id, (sheet_idx, row_idx)),
+                    None => rows_without_id.insert(&row.row, (sheet_idx, row_idx)),
+                };
+            }
+        }
+
+        let mut matched_rows: HashSet<(usize, usize)> = HashSet::new();
+        // Rows each sheet has after the changes, to figure out where the new climbs end up
+        let mut sheet_rows: Vec<Vec<RowOrder>> = vec![vec![]; gym_sheets.len()];
+        for wall in sectors.iter().flat_map(|s| s.walls.iter()) {
+            let Some(sheet_name) = self.config().gym_sheet_name(gym, &wall.category)? else {
+                warn!(?wall.name, ?wall.category, "wall category not configured, skipping");
+                continue;
+            };
+            let Some(wall_sheet_idx) = sheet_diffs.iter().position(|d| d.sheet_name == sheet_name)
+            else {
+                warn!(?wall.name, ?wall.category, "sheet for wall not found, skipping");
+                continue;
+            };
+
+            for climb in wall.climbs() {
+                let matched_row = rows_by_id
+                    .get(&climb.id)
+                    .or_else(|| rows_without_id.get(&ClimbSheetRow::from_climb(climb, columns)));
+
+                match matched_row {
+                    Some(&(sheet_idx, row_idx)) => {
+                        matched_rows.insert((sheet_idx, row_idx));
+                        let row = &gym_sheets[sheet_idx].rows[row_idx];
+                        let sheet_diff = &mut sheet_diffs[sheet_idx];
+                        sheet_rows[sheet_idx].push(RowOrder::of_climb(
+                            climb,
+                            columns,
+                            new_climb_days,
+                        ));
+
+                        let changes = self.row_changes(columns, climb, row);
+                        if changes.is_empty() {
+                            debug!(?climb, "climb already exists in sheet, skipping");
+                        } else {
+                            info!(?climb.id, ?row.row_idx, ?changes, "climb changed, updating row");
+                            sheet_diff.updated_rows.push(RowUpdate {
+                                row_idx: row.row_idx,
+                                climb: climb.to_owned(),
+                                changes,
+                            });
+                        }
+                    }
+                    None => {
+                        info!(?climb, "climb not in sheet, adding");
+                        let sheet_diff = &mut sheet_diffs[wall_sheet_idx];
+                        sheet_rows[wall_sheet_idx].push(RowOrder::of_climb(
+                            climb,
+                            columns,
+                            new_climb_days,
+                        ));
+                        sheet_diff.new_climbs.push(climb.to_owned());
+                    }
+                }
+            }
+        }
+
+        // See SheetDiff::removed_rows
+        let has_climbs = sectors
+            .iter()
+            .flat_map(|s| s.walls.iter())
+            .any(|w| w.climbs().next().is_some());
+        if !has_climbs {
+            warn!(?gym.id, "no climbs for gym, not removing anything");
+        }
+
+        for (sheet_idx, ((gym_sheet, sheet_diff), mut rows)) in gym_sheets
+            .into_iter()
+            .zip(sheet_diffs.iter_mut())
+            .zip(sheet_rows)
+            .enumerate()
+        {
+            for (row_idx, row) in gym_sheet.rows.into_iter().enumerate() {
+                if matched_rows.contains(&(sheet_idx, row_idx)) {
+                    continue;
+                }
+                if has_climbs {
+                    info!(?row, "climb no longer exists, removing from sheet");
+                    sheet_diff.removed_rows.push(row);
+                } else {
+                    rows.push(RowOrder::of_row(&row, columns, new_climb_days));
+                }
+            }
+
+            // Updated rows may have a different date or grade
+            if !sheet_diff.new_climbs.is_empty() || !sheet_diff.updated_rows.is_empty() {
+                sheet_diff.sort_column_idxs = sort_fields(self.config().sort_by)
+                    .iter()
+                    .filter_map(|field| column_position(columns, *field))
+                    .map(|idx| sheet_diff.first_column_idx + idx as i32)
+                    .collect();
+            }
+            sheet_diff.new_row_idxs = new_row_idxs(rows, self.config().sort_by);
+        }
+
+        let archive = match &self.config().archive_sheet_suffix {
+            Some(archive_sheet_suffix) => {
+                self.archive_diff(gym, archive_sheet_suffix, &sheet_diffs)?
+            }
+            None => None,
+        };
+
+        Ok(GymDiff {
+            gym_id: gym.id,
+            gym_name: gym.name.clone(),
+            sheets: sheet_diffs,
+            archive,
+        })
+    }
+
+    /// Compare row's cells to what they would be if the climb was added now. Values are compared
+    /// as they would be entered by the user, so that hyperlinks are compared by url.
+    fn row_changes(
+        &self,
+        columns: &[config::Column],
+        climb: &vertical_life::Climb,
+        row: &GymSheetRow,
+    ) -> Vec<CellChange> {
+        let mut changes = vec![];
+        for (column_idx, column) in columns.iter().enumerate() {
+            let old = row.values.get(column_idx).cloned().unwrap_or_default();
+            let new = column.sheet_value(climb);
+            if old != new {
+                changes.push(CellChange {
+                    column: column_idx,
+                    old,
+                    new,
+                });
+            }
+
+            if column.has_color() {
+                let old_color = row.colors.get(column_idx).cloned().unwrap_or_default();
+                if !old_color.eq_ignore_ascii_case(&climb.color) {
+                    changes.push(CellChange {
+                        column: column_idx,
+                        old: old_color,
+                        new: climb.color.clone(),
+                    });
+                }
+            }
+        }
+        changes
+    }
+
+    /// Rows to append to gym's archive tab, for example "Ristikko - Arkisto"
+    fn archive_diff(
+        &self,
+        gym: &vertical_life::Gym,
+        archive_sheet_suffix: &str,
+        sheet_diffs: &[SheetDiff],
+    ) -> Result<Option<ArchiveDiff>> {
+        let removed_at = chrono::Utc::now().date_naive();
+        let rows = sheet_diffs
+            .iter()
+            .flat_map(|d| d.removed_rows.iter())
+            .map(|r| r.to_archive_row(removed_at, self.config().gym_columns(gym.id).len()))
+            .collect::<Vec<_>>();
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let sheet_name =
+            config::format_sheet_name(&self.config().tab_prefix(gym)?, archive_sheet_suffix);
+        Ok(Some(ArchiveDiff { sheet_name, rows }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_row_idxs_test() {
+        let today = chrono::Utc::now().date_naive();
+        let row = |grade_key, days_ago, is_new| RowOrder {
+            grade_key,
+            set_at: Some(today - chrono::Duration::days(days_ago)),
+            is_new,
+        };
+        let rows = vec![
+            row(Some(10), 30, false),
+            row(Some(14), 1, true),
+            row(None, 2, true),
+            row(Some(12), 40, false),
+            row(Some(10), 3, true),
+        ];
+        assert_eq!(new_row_idxs(rows.clone(), SortBy::Date), vec![0, 1, 2]);
+        assert_eq!(new_row_idxs(rows, SortBy::Grade), vec![0, 2, 4]);
+    }
+}
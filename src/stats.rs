@@ -0,0 +1,159 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use eyre::Result;
+use tracing::*;
+
+use crate::{
+    config::Config,
+    grades::difficulty_sort_key,
+    sheets::Row,
+    vertical_life::{self, Climb},
+};
+
+/// Columns of the statistics tab before the grades
+const KEY_COLUMN_TITLES: [&str; 3] = ["Välilehti", "Ryhmä", "Arvo"];
+const TOTAL_COLUMN_TITLE: &str = "Yhteensä";
+const ALL_GROUP: &str = "Kaikki";
+const SECTOR_GROUP: &str = "Sektori";
+const COLOR_GROUP: &str = "Väri";
+
+/// Gym's climbs grouped like they are grouped to tabs in the spreadsheet
+#[derive(Debug)]
+pub struct Tab {
+    /// Name of the tab, for example "Ristikko - Boulderit"
+    pub title: String,
+    pub gym_id: u32,
+    pub climbs: Vec<Climb>,
+}
+
+/// Gym's climbs grouped to tabs, newest first. Walls with a wall category that is not configured
+/// are skipped.
+pub fn gym_tabs(
+    config: &Config,
+    gym: &vertical_life::Gym,
+    sectors: &[vertical_life::GymSectorFull],
+) -> Result<Vec<Tab>> {
+    let mut tabs: BTreeMap<String, Vec<Climb>> = BTreeMap::new();
+    for wall in sectors.iter().flat_map(|s| s.walls.iter()) {
+        let Some(title) = config.gym_sheet_name(gym, &wall.category)? else {
+            warn!(?wall.name, ?wall.category, "wall category not configured, skipping");
+            continue;
+        };
+        tabs.entry(title)
+            .or_default()
+            .extend(wall.climbs().cloned());
+    }
+    Ok(tabs
+        .into_iter()
+        .map(|(title, mut climbs)| {
+            // Newest first, like the sheets are sorted
+            climbs.sort_by_key(|c| std::cmp::Reverse(c.set_at));
+            Tab {
+                title,
+                gym_id: gym.id,
+                climbs,
+            }
+        })
+        .collect())
+}
+
+fn group_by(climbs: &[Climb], key: impl Fn(&Climb) -> String) -> BTreeMap<String, Vec<&Climb>> {
+    let mut groups: BTreeMap<String, Vec<&Climb>> = BTreeMap::new();
+    for climb in climbs {
+        groups.entry(key(climb)).or_default().push(climb);
+    }
+    groups
+}
+
+/// Header and rows of the statistics tab: for each tab, the number of climbs per grade in total,
+/// per sector and per hold color. Grades of all tabs are columns of their own in order of
+/// difficulty, so that the rows line up for filters and formulas.
+pub fn stats_rows<'a>(tabs: impl IntoIterator<Item = (&'a str, &'a [Climb])>) -> (Row, Vec<Row>) {
+    let tabs = tabs.into_iter().collect::<Vec<_>>();
+    let mut grades = tabs
+        .iter()
+        .flat_map(|(_, climbs)| climbs.iter().map(difficulty_sort_key))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|(_, _, difficulty)| difficulty)
+        .collect::<Vec<_>>();
+    // Boulders and routes can have grades with the same name
+    let mut seen = HashSet::new();
+    grades.retain(|grade| seen.insert(grade.clone()));
+
+    let header = KEY_COLUMN_TITLES
+        .into_iter()
+        .map(String::from)
+        .chain(grades.iter().cloned())
+        .chain([TOTAL_COLUMN_TITLE.to_string()])
+        .collect();
+
+    let mut rows = vec![];
+    for (tab_name, climbs) in tabs {
+        let mut groups = vec![(ALL_GROUP, String::new(), climbs.iter().collect::<Vec<_>>())];
+        groups.extend(
+            group_by(climbs, |c| c.sector_name.clone())
+                .into_iter()
+                .map(|(sector, climbs)| (SECTOR_GROUP, sector, climbs)),
+        );
+        groups.extend(
+            group_by(climbs, |c| c.color.to_lowercase())
+                .into_iter()
+                .map(|(color, climbs)| (COLOR_GROUP, color, climbs)),
+        );
+
+        for (group, value, climbs) in groups {
+            let counts = grades
+                .iter()
+                .map(|grade| climbs.iter().filter(|c| c.difficulty == *grade).count());
+            rows.push(
+                [tab_name.to_string(), group.to_string(), value]
+                    .into_iter()
+                    .chain(counts.map(|n| n.to_string()))
+                    .chain([climbs.len().to_string()])
+                    .collect(),
+            );
+        }
+    }
+    (header, rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn climb(difficulty: &str, sector_name: &str, color: &str) -> Climb {
+        Climb {
+            difficulty: difficulty.to_string(),
+            color: color.to_string(),
+            sector_name: sector_name.to_string(),
+            ..Climb::test(1)
+        }
+    }
+
+    #[test]
+    fn stats_rows_test() {
+        let climbs = vec![
+            climb("6A", "Sektori 1", "#FF0000"),
+            climb("6B", "Sektori 1", "#ff0000"),
+            climb("6A", "Sektori 2", "#0000ff"),
+        ];
+        let (header, rows) = stats_rows([("Ristikko - Boulderit", climbs.as_slice())]);
+
+        assert_eq!(
+            header,
+            ["Välilehti", "Ryhmä", "Arvo", "6A", "6B", "Yhteensä"]
+        );
+        let rows = rows.iter().map(|r| r.join("|")).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "Ristikko - Boulderit|Kaikki||2|1|3",
+                "Ristikko - Boulderit|Sektori|Sektori 1|1|1|2",
+                "Ristikko - Boulderit|Sektori|Sektori 2|1|0|1",
+                "Ristikko - Boulderit|Väri|#0000ff|1|0|1",
+                "Ristikko - Boulderit|Väri|#ff0000|1|1|2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,17 @@
+/// Lowercase name with words joined by dashes, for example "kiipeilyareena-ristikko" for
+/// "Kiipeilyareena Ristikko", to be used in file names
+pub fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Escape text to be placed in HTML elements and attribute values
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -6,7 +6,7 @@ use reqwest::{
     StatusCode,
 };
 
-use super::{types::Gym, GymSectorFull, VerticalLifeAuthClient};
+use super::{types::Gym, GymSearchResult, GymSectorFull, VerticalLifeAuthClient};
 
 pub const BASE_URL: &str = "https://vlcapi.vertical-life.info";
 const USER_AGENT_VALUE: &str = "Vertical Life Climbing/6.14.0 (iPhone12,3; iOS 16.1.1; Scale/3.00)";
@@ -17,6 +17,8 @@ pub struct VerticalLifeClient {
     pub client: reqwest::Client,
     pub access_token: String,
     pub refresh_token: String,
+    /// API the requests are sent to, BASE_URL unless replaced with with_base_url
+    pub base_url: String,
 }
 
 impl VerticalLifeClient {
@@ -28,6 +30,15 @@ impl VerticalLifeClient {
                 .unwrap(),
             access_token,
             refresh_token,
+            base_url: BASE_URL.to_string(),
+        }
+    }
+
+    /// Send the requests to another server, for example a local stand-in in tests
+    pub fn with_base_url(self, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.to_string(),
+            ..self
         }
     }
 
@@ -67,11 +78,12 @@ impl VerticalLifeClient {
 
     pub async fn get_gym_details(&mut self, gym_id: u32) -> Result<Gym> {
         info!(?gym_id, "getting gym details");
+        let base_url = self.base_url.clone();
         let res = self
             .make_request(|client| {
                 let params = [("details", "overview")];
                 client
-                    .get(&format!("{}/gyms/{}", BASE_URL, gym_id))
+                    .get(format!("{}/gyms/{}", base_url, gym_id))
                     .form(&params)
             })
             .await?;
@@ -79,11 +91,27 @@ impl VerticalLifeClient {
         Ok(gym_sector)
     }
 
+    /// Gyms whose name matches the search, for finding the id of a gym
+    pub async fn search_gyms(&mut self, name: &str) -> Result<Vec<GymSearchResult>> {
+        info!(?name, "searching gyms");
+        let base_url = self.base_url.clone();
+        let res = self
+            .make_request(|client| {
+                client
+                    .get(format!("{}/gyms/search", base_url))
+                    .query(&[("query", name)])
+            })
+            .await?;
+        let gyms = res.json().await?;
+        Ok(gyms)
+    }
+
     pub async fn get_gym_sector(&mut self, gym_sector_id: u32) -> Result<GymSectorFull> {
         info!(?gym_sector_id, "getting gym sector");
+        let base_url = self.base_url.clone();
         let res = self
             .make_request(|client| {
-                client.get(&format!("{}/gym_sectors/{}", BASE_URL, gym_sector_id))
+                client.get(format!("{}/gym_sectors/{}", base_url, gym_sector_id))
             })
             .await?;
         let gym_sector = res.json().await?;
@@ -106,3 +134,77 @@ fn make_headers(access_token: &str) -> HeaderMap {
     headers.insert("x-app-version", HeaderValue::from_static("6.14.0"));
     headers
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Accept a single request, answer it with body as JSON and return the request's head
+    async fn respond(listener: TcpListener, body: &'static str) -> String {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![];
+        let mut buf = [0; 1024];
+        let head_end = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(idx) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break idx;
+            }
+        };
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8(request[..head_end].to_vec()).unwrap()
+    }
+
+    async fn client_and_server(
+        body: &'static str,
+    ) -> (VerticalLifeClient, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(respond(listener, body));
+        let client = VerticalLifeClient::new("access".to_string(), "refresh".to_string())
+            .with_base_url(&base_url);
+        (client, server)
+    }
+
+    // The fixtures are written by hand, not captured from the app's traffic, so this checks the
+    // request the client sends and not that the API answers it like this
+    #[tokio::test]
+    async fn search_gyms_test() {
+        let (mut client, server) =
+            client_and_server(include_str!("fixtures/gym_search.json")).await;
+        let results = client.search_gyms("kivi vallila").await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(
+            request.starts_with("GET /gyms/search?query=kivi+vallila HTTP/1.1"),
+            "{request}"
+        );
+        assert!(request
+            .to_lowercase()
+            .contains("authorization: bearer access"));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, 2108);
+        assert_eq!(results[0].name, "Kiipeilyareena Ristikko");
+        assert_eq!(results[0].city.as_deref(), Some("Helsinki"));
+        assert_eq!(results[1].city, None);
+    }
+
+    #[tokio::test]
+    async fn get_gym_details_test() {
+        let (mut client, server) = client_and_server(include_str!("fixtures/gym_3456.json")).await;
+        let gym = client.get_gym_details(3456).await.unwrap();
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("GET /gyms/3456 HTTP/1.1"), "{request}");
+        assert_eq!(gym.id, 3456);
+        assert_eq!((gym.boulder_count, gym.route_count), (88, 42));
+    }
+}
@@ -212,7 +212,7 @@ fn parse_action_url(body: &str) -> Result<String> {
 }
 
 fn base64_encode(data: &[u8]) -> String {
-    general_purpose::URL_SAFE_NO_PAD.encode(&data)
+    general_purpose::URL_SAFE_NO_PAD.encode(data)
 }
 
 fn random_bytes(len: usize) -> Vec<u8> {
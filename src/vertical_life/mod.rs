@@ -1,10 +1,12 @@
 mod api;
 mod auth;
+mod search;
 mod types;
 mod util;
 
 pub use api::VerticalLifeClient;
 pub use auth::VerticalLifeAuthClient;
+pub use search::*;
 pub use types::*;
 pub use util::*;
 
@@ -0,0 +1,72 @@
+use std::fmt;
+
+use super::{Gym, GymSearchResult};
+
+/// Gym found by a search, with the climb counts from its details
+#[derive(Debug, PartialEq, Eq)]
+pub struct GymSummary {
+    pub id: u32,
+    pub name: String,
+    pub city: Option<String>,
+    pub boulder_count: u32,
+    pub route_count: u32,
+}
+
+impl GymSummary {
+    pub fn new(result: &GymSearchResult, gym: &Gym) -> Self {
+        Self {
+            id: result.id,
+            name: result.name.clone(),
+            city: result.city.clone(),
+            boulder_count: gym.boulder_count,
+            route_count: gym.route_count,
+        }
+    }
+}
+
+/// A line that can be pasted to the gyms list in config.toml as is, with the gym's details in a
+/// comment, for example "2108, # Kiipeilyareena Ristikko, Helsinki: 231 boulders, 0 routes"
+impl fmt::Display for GymSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, # {}", self.id, self.name)?;
+        if let Some(city) = &self.city {
+            write!(f, ", {city}")?;
+        }
+        write!(
+            f,
+            ": {} boulders, {} routes",
+            self.boulder_count, self.route_count
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gym_summary_test() {
+        let results: Vec<GymSearchResult> =
+            serde_json::from_str(include_str!("fixtures/gym_search.json")).unwrap();
+        let gyms: Vec<Gym> = [
+            include_str!("fixtures/gym_2108.json"),
+            include_str!("fixtures/gym_3456.json"),
+        ]
+        .into_iter()
+        .map(|json| serde_json::from_str(json).unwrap())
+        .collect();
+
+        let lines = results
+            .iter()
+            .zip(&gyms)
+            .map(|(result, gym)| GymSummary::new(result, gym).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "2108, # Kiipeilyareena Ristikko, Helsinki: 231 boulders, 0 routes",
+                "3456, # Kivi Vallila: 88 boulders, 42 routes",
+            ]
+        );
+    }
+}
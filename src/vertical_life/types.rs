@@ -1,3 +1,5 @@
+use std::fmt;
+
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
@@ -53,6 +55,14 @@ pub struct Gym {
     pub gym_sectors: Vec<GymSector>,
 }
 
+/// Gym in the results of a gym search, without its climb counts
+#[derive(Debug, Clone, Deserialize)]
+pub struct GymSearchResult {
+    pub id: u32,
+    pub name: String,
+    pub city: Option<String>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Climb {
     pub id: u32,
@@ -69,6 +79,40 @@ pub struct Climb {
     pub item_type: String,
 }
 
+#[cfg(test)]
+impl Climb {
+    /// Boulder for tests, set now, with the label and share url following the id. Other fields
+    /// are overridden with struct update syntax.
+    pub(crate) fn test(id: u32) -> Self {
+        Self {
+            id,
+            difficulty: "6A".to_string(),
+            set_at: Utc::now(),
+            color: "#ff0000".to_string(),
+            sector_name: "Sektori 1".to_string(),
+            parent_name: "Seinä 1".to_string(),
+            route_card_label: format!("A{id}"),
+            route_setter: "Matti".to_string(),
+            share_url: format!("https://example.com/{id}"),
+            item_type: "gym_boulder".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Climb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} | {} | {} | {} | {}",
+            self.route_card_label,
+            self.difficulty,
+            self.set_at.format("%-d.%-m.%Y"),
+            self.route_setter,
+            self.parent_name
+        )
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct ZlagsResponse {
     pub gym_boulders: Vec<Climb>,